@@ -0,0 +1,178 @@
+//! Laying out the network in the viewer, optionally animated: nodes ease
+//! towards the layout's positions while it is computed.
+
+use bevy::prelude::*;
+use rand::thread_rng;
+
+use crate::{
+    model::layout::{self, Algorithm, Layout},
+    wire_placement, ModelState, NetworkConfig, SimEdge, SimNode,
+};
+
+/// Share of the remaining distance to its target a node covers per frame.
+const EASING: f32 = 0.15;
+
+/// Node updates per frame spent on a layout that isn't animated.
+const UNANIMATED_WORK: usize = 1 << 14;
+
+pub struct Arranger {
+    pub animate: bool,
+    running: Option<Layout>,
+}
+
+impl Default for Arranger {
+    fn default() -> Self {
+        Self {
+            animate: true,
+            running: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ArrangeEvent {
+    Run(Algorithm),
+    ToggleAnimate,
+}
+
+pub struct ArrangeButton(pub ArrangeEvent);
+
+pub fn arrange_buttons(
+    interactions: Query<(&Interaction, &ArrangeButton), (Changed<Interaction>, With<Button>)>,
+    mut events: EventWriter<ArrangeEvent>,
+) {
+    for (interaction, button) in interactions.iter() {
+        if let Interaction::Clicked = interaction {
+            events.send(button.0);
+        }
+    }
+}
+
+pub fn arrange(
+    mut events: EventReader<ArrangeEvent>,
+    mut arranger: ResMut<Arranger>,
+    config: Res<NetworkConfig>,
+    model_opt: Option<ResMut<ModelState>>,
+    mut nodes: Query<(&SimNode, &mut Transform), Without<SimEdge>>,
+    mut wires: Query<(&SimEdge, &mut Transform), Without<SimNode>>,
+) {
+    let mut model = if let Some(model) = model_opt {
+        model
+    } else {
+        return;
+    };
+
+    // A replaced network keeps its own positions.
+    if model.is_added() {
+        arranger.running = None;
+    }
+
+    for event in events.iter() {
+        match event {
+            ArrangeEvent::Run(algorithm) => {
+                let layout = Layout::new(
+                    *algorithm,
+                    &model.display_model,
+                    layout::SPACING,
+                    &mut thread_rng(),
+                );
+
+                arranger.running = Some(layout);
+            }
+            ArrangeEvent::ToggleAnimate => arranger.animate = !arranger.animate,
+        }
+    }
+
+    let animate = arranger.animate;
+    let running = if let Some(running) = &mut arranger.running {
+        running
+    } else {
+        return;
+    };
+
+    let settling = if animate {
+        running.step()
+    } else {
+        // Nodes only move once done, which may take a large network several
+        // frames.
+        let iterations = UNANIMATED_WORK / running.positions().len().max(1);
+        if (0..iterations.max(1)).all(|_| running.step()) {
+            return;
+        }
+        false
+    };
+
+    let mut moving = false;
+    let eased = model
+        .display_model
+        .graph
+        .raw_nodes()
+        .iter()
+        .zip(running.positions())
+        .map(|(node, &target)| {
+            let position = node.weight.position.lerp(target, EASING);
+            moving |= position.distance(target) > 0.01;
+            position
+        })
+        .collect::<Vec<_>>();
+
+    let positions = if animate && (settling || moving) {
+        eased
+    } else {
+        let finished = arranger.running.take().unwrap();
+        finished.positions().to_vec()
+    };
+
+    layout::apply(&mut model.display_model, &positions);
+    layout::apply(&mut model.compute_model, &positions);
+
+    let graph = &model.display_model.graph;
+    let position = |graph_id: u32| graph.node_weight(graph_id.into()).map(|node| node.position);
+
+    for (node, mut transform) in nodes.iter_mut() {
+        if let Some(position) = position(node.graph_id) {
+            transform.translation = position;
+        }
+    }
+
+    for (wire, mut transform) in wires.iter_mut() {
+        let edge = match graph.raw_edges().get(wire.graph_id as usize) {
+            Some(edge) => edge,
+            None => continue,
+        };
+
+        let (placement, len) = wire_placement(
+            graph[edge.source()].position,
+            graph[edge.target()].position,
+            config.bulb_radius,
+        );
+
+        *transform = placement;
+        transform.scale.y = len.max(0.01) / wire.len.max(0.01);
+    }
+}
+
+pub fn arrange_labels(
+    arranger: Res<Arranger>,
+    buttons: Query<(&ArrangeButton, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    if !arranger.is_changed() {
+        return;
+    }
+
+    for (button, children) in buttons.iter() {
+        if let ArrangeEvent::ToggleAnimate = button.0 {
+            for child in children.iter() {
+                if let Ok(mut text) = texts.get_mut(*child) {
+                    text.sections[0].value = if arranger.animate {
+                        "Animate: on"
+                    } else {
+                        "Animate: off"
+                    }
+                    .to_string();
+                }
+            }
+        }
+    }
+}
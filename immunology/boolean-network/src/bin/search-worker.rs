@@ -0,0 +1,7 @@
+//! The web build's cycle search, run by the page in a Web Worker. Natively the
+//! search runs on a thread instead, so there is nothing to do.
+
+fn main() {
+    #[cfg(target_arch = "wasm32")]
+    boolean_network::serve_search_worker();
+}
@@ -0,0 +1,305 @@
+//! Building and changing the network by hand.
+//!
+//! While editing, clicking empty ground adds a node, dragging from a node to
+//! another adds an edge, dragging a node onto empty ground moves it, and
+//! right-clicking a node or edge deletes it. The number keys set the gate
+//! (1: and, 2: or, 3: nor) of the node under the cursor, or else of added
+//! nodes. Every edit restarts the simulation from the edited network.
+
+use std::ops::DerefMut;
+
+use bevy::prelude::*;
+use petgraph::graph::NodeIndex;
+
+use crate::{
+    model::{
+        edit::Edit,
+        history::{History, StreamingCycleFinder},
+        Model, NodeKind,
+    },
+    picking, replace_model, ModelState, NetworkConfig, Playback,
+};
+
+pub struct Editor {
+    pub enabled: bool,
+    /// Gate of added nodes.
+    pub gate: NodeKind,
+    /// Node the current drag started from.
+    drag: Option<NodeIndex>,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            gate: NodeKind::Or(false),
+            drag: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum EditorEvent {
+    Toggle,
+    Undo,
+    Redo,
+}
+
+pub struct EditorButton(pub EditorEvent);
+pub struct EditorText;
+
+enum Action {
+    Edit(Edit),
+    Undo,
+    Redo,
+}
+
+pub fn editor_buttons(
+    interactions: Query<(&Interaction, &EditorButton), (Changed<Interaction>, With<Button>)>,
+    mut events: EventWriter<EditorEvent>,
+) {
+    for (interaction, button) in interactions.iter() {
+        if let Interaction::Clicked = interaction {
+            events.send(button.0);
+        }
+    }
+}
+
+/// Ctrl+Z undoes, Ctrl+Y or Ctrl+Shift+Z redoes.
+pub fn editor_keys(
+    keys: Res<Input<KeyCode>>,
+    editor: Res<Editor>,
+    mut events: EventWriter<EditorEvent>,
+) {
+    if !editor.enabled || !(keys.pressed(KeyCode::LControl) || keys.pressed(KeyCode::RControl)) {
+        return;
+    }
+
+    let shift = keys.pressed(KeyCode::LShift) || keys.pressed(KeyCode::RShift);
+
+    if keys.just_pressed(KeyCode::Z) {
+        events.send(if shift {
+            EditorEvent::Redo
+        } else {
+            EditorEvent::Undo
+        });
+    } else if keys.just_pressed(KeyCode::Y) {
+        events.send(EditorEvent::Redo);
+    }
+}
+
+fn pointer_edit(
+    editor: &mut Editor,
+    model: &Model,
+    mouse: &Input<MouseButton>,
+    keys: &Input<KeyCode>,
+    ray: Option<(Vec3, Vec3)>,
+    bulb_radius: f32,
+) -> Option<Edit> {
+    if mouse.just_released(MouseButton::Left) {
+        let from = editor.drag.take()?;
+        let (origin, direction) = ray?;
+
+        return match picking::pick_node(model, origin, direction, bulb_radius) {
+            Some(to) if to != from => Some(Edit::AddEdge(from, to)),
+            Some(_) => None,
+            None => picking::ray_plane(origin, direction, model.graph[from].position.y)
+                .map(|position| Edit::MoveNode(from, position)),
+        };
+    }
+
+    let (origin, direction) = ray?;
+    let node = picking::pick_node(model, origin, direction, bulb_radius);
+
+    if mouse.just_pressed(MouseButton::Left) {
+        match node {
+            Some(node) => {
+                editor.drag = Some(node);
+                None
+            }
+            None => picking::ray_plane(origin, direction, 0.0).map(|position| Edit::AddNode {
+                kind: editor.gate,
+                position,
+            }),
+        }
+    } else if mouse.just_pressed(MouseButton::Right) {
+        match node {
+            Some(node) => Some(Edit::RemoveNode(node)),
+            None => picking::pick_edge(model, origin, direction, bulb_radius).map(Edit::RemoveEdge),
+        }
+    } else {
+        let gate = [
+            (KeyCode::Key1, NodeKind::And(false)),
+            (KeyCode::Key2, NodeKind::Or(false)),
+            (KeyCode::Key3, NodeKind::Nor(false)),
+        ]
+        .iter()
+        .find(|(key, _)| keys.just_pressed(*key))
+        .map(|&(_, gate)| gate)?;
+
+        match node {
+            Some(node) => Some(Edit::SetGate(node, gate)),
+            None => {
+                editor.gate = gate;
+                None
+            }
+        }
+    }
+}
+
+/// Starts a new run from the edited network's node states.
+fn restart(commands: &mut Commands, model: &mut ModelState) {
+    let mut display_model = model.display_model.clone();
+    display_model.timestep = 0;
+    display_model.history = History::default().with_states();
+
+    let mut compute_model = display_model.clone();
+    compute_model.history = History::default();
+
+    replace_model(
+        commands,
+        ModelState {
+            cycle_finder: StreamingCycleFinder::new(&display_model),
+            display_model,
+            compute_model,
+            cycle: None,
+            cycle_activity: None,
+            params: model.params.clone(),
+            seed: model.seed,
+            perturbed: false,
+            edits: std::mem::take(&mut model.edits),
+            worker: None,
+        },
+    );
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn edit_graph(
+    mut commands: Commands,
+    mut events: EventReader<EditorEvent>,
+    mouse: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    windows: Res<Windows>,
+    cameras: picking::Cameras,
+    interactions: Query<&Interaction>,
+    mut editor: ResMut<Editor>,
+    mut playback: ResMut<Playback>,
+    config: Res<NetworkConfig>,
+    model_opt: Option<ResMut<ModelState>>,
+) {
+    let mut action = None;
+
+    for event in events.iter() {
+        match event {
+            EditorEvent::Toggle => {
+                editor.enabled = !editor.enabled;
+                editor.drag = None;
+
+                // Keep node states still while editing.
+                if editor.enabled {
+                    playback.playing = false;
+                }
+            }
+            EditorEvent::Undo => action = Some(Action::Undo),
+            EditorEvent::Redo => action = Some(Action::Redo),
+        }
+    }
+
+    let mut model = if let Some(model) = model_opt {
+        model
+    } else {
+        return;
+    };
+
+    if action.is_none() && editor.enabled {
+        let ray = if picking::over_ui(&interactions) {
+            None
+        } else {
+            picking::cursor_ray(&windows, &cameras)
+        };
+
+        action = pointer_edit(
+            &mut editor,
+            &model.display_model,
+            &mouse,
+            &keys,
+            ray,
+            config.bulb_radius,
+        )
+        .map(Action::Edit);
+    }
+
+    let action = if let Some(action) = action {
+        action
+    } else {
+        return;
+    };
+
+    let ModelState {
+        display_model,
+        edits,
+        ..
+    } = model.deref_mut();
+
+    // Edits apply to the node states on screen.
+    if let Some(timestep) = playback.cursor.take() {
+        if let Err(err) = display_model.rewind(timestep) {
+            warn!("failed to rewind to {}: {}", timestep, err);
+            return;
+        }
+    }
+
+    let changed = match action {
+        Action::Edit(edit) => match edits.apply(display_model, &edit) {
+            Ok(()) => true,
+            Err(err) => {
+                warn!("failed to edit the network: {}", err);
+                false
+            }
+        },
+        Action::Undo => edits.undo(display_model),
+        Action::Redo => edits.redo(display_model),
+    };
+
+    if changed {
+        editor.drag = None;
+        restart(&mut commands, &mut model);
+    }
+}
+
+pub fn editor_labels(
+    editor: Res<Editor>,
+    buttons: Query<(&EditorButton, &Children)>,
+    mut texts: Query<&mut Text>,
+    help_texts: Query<Entity, With<EditorText>>,
+) {
+    if !editor.is_changed() {
+        return;
+    }
+
+    for (button, children) in buttons.iter() {
+        if let EditorEvent::Toggle = button.0 {
+            for child in children.iter() {
+                if let Ok(mut text) = texts.get_mut(*child) {
+                    text.sections[0].value =
+                        if editor.enabled { "Done" } else { "Edit" }.to_string();
+                }
+            }
+        }
+    }
+
+    for entity in help_texts.iter() {
+        if let Ok(mut text) = texts.get_mut(entity) {
+            text.sections[0].value = if editor.enabled {
+                let gate = match editor.gate {
+                    NodeKind::And(_) => "and",
+                    NodeKind::Or(_) => "or",
+                    NodeKind::Nor(_) => "nor",
+                };
+                format!("adding {} gates (1: and, 2: or, 3: nor)", gate)
+            } else {
+                String::new()
+            };
+        }
+    }
+}
@@ -0,0 +1,335 @@
+//! Panel of generator parameters, opened from the bottom row. The settings of
+//! the last generated network are saved and restored on the next start.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    model::generate::{GeneratorParams, Param, Topology},
+    storage, ButtonMaterials, ModelState, RegenerateEvent,
+};
+
+const SETTINGS_PATH: &str = "generator.json";
+
+/// Largest network the panel and the web page's query string allow.
+pub const MAX_NODE_COUNT: usize = 5000;
+
+const MAX_IN_DEGREE: usize = 8;
+
+/// Parameters stepped by the panel's buttons: label, step and bounds.
+const STEPS: [(Param, &str, f64, f64, f64); 7] = [
+    (Param::NodeCount, "nodes", 10.0, 1.0, MAX_NODE_COUNT as f64),
+    (Param::MinDist, "min distance", 0.25, 0.25, 20.0),
+    (Param::MaxConnectDist, "connect radius", 0.25, 0.0, 20.0),
+    (Param::ActiveProb, "active prob", 0.05, 0.0, 1.0),
+    (Param::AndWeight, "and weight", 0.5, 0.0, 10.0),
+    (Param::OrWeight, "or weight", 0.5, 0.0, 10.0),
+    (Param::NorWeight, "nor weight", 0.5, 0.0, 10.0),
+];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GeneratorSettings {
+    pub params: GeneratorParams,
+    /// Seed of the next network, or `None` for a fresh one each time.
+    pub seed: Option<u64>,
+}
+
+impl GeneratorSettings {
+    /// The settings saved by the last session, or the defaults.
+    pub fn load() -> Self {
+        let json = match storage::read(SETTINGS_PATH) {
+            Ok(Some(json)) => json,
+            Ok(None) => return Self::default(),
+            Err(err) => {
+                warn!("failed to read {}: {}", SETTINGS_PATH, err);
+                return Self::default();
+            }
+        };
+
+        let settings = serde_json::from_str::<Self>(&json)
+            .map_err(|err| err.to_string())
+            .and_then(|settings| settings.params.validate().map(|()| settings));
+
+        settings.unwrap_or_else(|err| {
+            warn!("ignoring {}: {}", SETTINGS_PATH, err);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) {
+        let result = serde_json::to_string_pretty(self)
+            .map_err(|err| err.to_string())
+            .and_then(|json| storage::write(SETTINGS_PATH, &json));
+
+        if let Err(err) = result {
+            warn!("failed to save {}: {}", SETTINGS_PATH, err);
+        }
+    }
+
+    fn label(&self, field: Field) -> String {
+        match field {
+            Field::Param(index) => {
+                let (param, label, ..) = STEPS[index];
+                match param {
+                    Param::NodeCount => format!("{}: {}", label, self.params.node_count),
+                    _ => format!("{}: {:.2}", label, self.params.get(param)),
+                }
+            }
+            Field::Topology => match self.params.topology {
+                Topology::Geometric => "topology: geometric".to_string(),
+                Topology::Kauffman { in_degree } => format!("topology: NK, K = {}", in_degree),
+            },
+            Field::Seed => match self.seed {
+                Some(seed) => format!("seed: {}", seed),
+                None => "seed: random".to_string(),
+            },
+        }
+    }
+
+    /// Steps `field` up or down. `current_seed` is where a fixed seed starts
+    /// from.
+    fn step(&mut self, field: Field, up: bool, current_seed: u64) {
+        match field {
+            Field::Param(index) => {
+                let (param, _, step, min, max) = STEPS[index];
+                let value = self.params.get(param) / step + if up { 1.0 } else { -1.0 };
+
+                let mut params = self.params.clone();
+                params.set(param, (value.round() * step).clamp(min, max));
+
+                // Keeps at least one gate weight positive.
+                if params.validate().is_ok() {
+                    self.params = params;
+                }
+            }
+            Field::Topology => {
+                let in_degree = match self.params.topology {
+                    Topology::Geometric => 0,
+                    Topology::Kauffman { in_degree } => in_degree,
+                };
+                let in_degree = if up {
+                    (in_degree + 1).min(MAX_IN_DEGREE)
+                } else {
+                    in_degree.saturating_sub(1)
+                };
+
+                self.params.topology = match in_degree {
+                    0 => Topology::Geometric,
+                    _ => Topology::Kauffman { in_degree },
+                };
+            }
+            Field::Seed => {
+                let seed = self.seed.unwrap_or(current_seed);
+                self.seed = Some(if up {
+                    seed.wrapping_add(1)
+                } else {
+                    seed.wrapping_sub(1)
+                });
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Field {
+    /// Index into [`STEPS`].
+    Param(usize),
+    /// Geometric below K = 1.
+    Topology,
+    Seed,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum PanelAction {
+    Toggle,
+    Step(Field, bool),
+    RandomSeed,
+    Generate,
+}
+
+pub struct PanelButton(pub PanelAction);
+pub struct FieldText(Field);
+pub struct Panel;
+
+pub struct PanelMaterials {
+    background: Handle<ColorMaterial>,
+    transparent: Handle<ColorMaterial>,
+}
+
+impl FromWorld for PanelMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+        PanelMaterials {
+            background: materials.add(Color::rgba(0.05, 0.05, 0.05, 0.85).into()),
+            transparent: materials.add(Color::NONE.into()),
+        }
+    }
+}
+
+fn uniform(px: f32) -> Rect<Val> {
+    Rect {
+        left: Val::Px(px),
+        right: Val::Px(px),
+        top: Val::Px(px),
+        bottom: Val::Px(px),
+    }
+}
+
+fn spawn_panel(
+    commands: &mut Commands,
+    button_materials: &ButtonMaterials,
+    panel_materials: &PanelMaterials,
+    asset_server: &AssetServer,
+    settings: &GeneratorSettings,
+) {
+    let font = asset_server.load("fonts/NotoSans-Bold.ttf");
+    let text = |value: String| {
+        Text::with_section(
+            value,
+            TextStyle {
+                font: font.clone(),
+                font_size: 28.0,
+                color: Color::rgb(0.9, 0.9, 0.9),
+            },
+            Default::default(),
+        )
+    };
+
+    let row = || NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Row,
+            align_items: AlignItems::Center,
+            margin: uniform(2.0),
+            ..Default::default()
+        },
+        material: panel_materials.transparent.clone(),
+        ..Default::default()
+    };
+
+    let button = |parent: &mut ChildBuilder, label: &str, width: f32, action: PanelAction| {
+        parent
+            .spawn_bundle(ButtonBundle {
+                style: Style {
+                    size: Size::new(Val::Px(width), Val::Px(36.0)),
+                    margin: Rect {
+                        left: Val::Px(4.0),
+                        ..Default::default()
+                    },
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..Default::default()
+                },
+                material: button_materials.normal.clone(),
+                ..Default::default()
+            })
+            .insert(PanelButton(action))
+            .with_children(|parent| {
+                parent.spawn_bundle(TextBundle {
+                    text: text(label.to_string()),
+                    ..Default::default()
+                });
+            });
+    };
+
+    let fields = (0..STEPS.len())
+        .map(Field::Param)
+        .chain([Field::Topology, Field::Seed]);
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(10.0),
+                    top: Val::Px(140.0),
+                    ..Default::default()
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                padding: uniform(6.0),
+                ..Default::default()
+            },
+            material: panel_materials.background.clone(),
+            ..Default::default()
+        })
+        .insert(Panel)
+        .with_children(|parent| {
+            for field in fields {
+                parent.spawn_bundle(row()).with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(300.0), Val::Auto),
+                                ..Default::default()
+                            },
+                            text: text(settings.label(field)),
+                            ..Default::default()
+                        })
+                        .insert(FieldText(field));
+
+                    button(parent, "-", 40.0, PanelAction::Step(field, false));
+                    button(parent, "+", 40.0, PanelAction::Step(field, true));
+
+                    if let Field::Seed = field {
+                        button(parent, "random", 110.0, PanelAction::RandomSeed);
+                    }
+                });
+            }
+
+            parent.spawn_bundle(row()).with_children(|parent| {
+                button(parent, "Generate", 200.0, PanelAction::Generate);
+            });
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn panel_buttons(
+    mut commands: Commands,
+    interactions: Query<(&Interaction, &PanelButton), (Changed<Interaction>, With<Button>)>,
+    panels: Query<Entity, With<Panel>>,
+    mut settings: ResMut<GeneratorSettings>,
+    button_materials: Res<ButtonMaterials>,
+    panel_materials: Res<PanelMaterials>,
+    asset_server: Res<AssetServer>,
+    model_opt: Option<Res<ModelState>>,
+    mut events: EventWriter<RegenerateEvent>,
+) {
+    for (interaction, button) in interactions.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        match button.0 {
+            PanelAction::Toggle => {
+                if panels.iter().next().is_some() {
+                    for panel in panels.iter() {
+                        commands.entity(panel).despawn_recursive();
+                    }
+                } else {
+                    spawn_panel(
+                        &mut commands,
+                        &button_materials,
+                        &panel_materials,
+                        &asset_server,
+                        &settings,
+                    );
+                }
+            }
+            PanelAction::Step(field, up) => {
+                let current_seed = model_opt.as_ref().map_or(0, |model| model.seed);
+                settings.step(field, up, current_seed);
+            }
+            PanelAction::RandomSeed => settings.seed = None,
+            PanelAction::Generate => events.send(RegenerateEvent(settings.clone())),
+        }
+    }
+}
+
+pub fn panel_labels(settings: Res<GeneratorSettings>, mut texts: Query<(&FieldText, &mut Text)>) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    for (field, mut text) in texts.iter_mut() {
+        text.sections[0].value = settings.label(field.0);
+    }
+}
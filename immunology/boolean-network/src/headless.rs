@@ -0,0 +1,517 @@
+use std::{collections::HashMap, fs::File, io::BufWriter, ops::Range, str::FromStr, time::Instant};
+
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::model::{
+    compiled::CompiledModel,
+    ensemble::{Ensemble, EnsembleConfig},
+    generate::{self, GeneratorParams, Topology},
+    history::{History, StreamingCycleFinder},
+    interchange,
+    layout::{self, Layout},
+    query::Query,
+    raster,
+    snapshot::Snapshot,
+    sweep::{Metric, Sweep},
+    trajectory::{self, Format},
+    Model,
+};
+
+/// `--key value` pairs following a subcommand.
+struct Flags(HashMap<String, String>);
+
+impl Flags {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut flags = HashMap::new();
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            let key = arg
+                .strip_prefix("--")
+                .ok_or_else(|| format!("expected flag, found {:?}", arg))?;
+            let value = args
+                .next()
+                .ok_or_else(|| format!("expected value for --{}", key))?;
+
+            flags.insert(key.to_string(), value.clone());
+        }
+
+        Ok(Self(flags))
+    }
+
+    fn get<T: FromStr>(&self, key: &str, default: T) -> Result<T, String> {
+        match self.0.get(key) {
+            Some(value) => value
+                .parse()
+                .map_err(|_| format!("invalid value for --{}: {:?}", key, value)),
+            None => Ok(default),
+        }
+    }
+}
+
+pub fn run(args: &[String]) -> Result<(), String> {
+    let (command, args) = args.split_first().ok_or("expected command")?;
+    let flags = Flags::parse(args)?;
+
+    match command.as_str() {
+        "ensemble" => ensemble(&flags),
+        "sweep" => sweep(&flags),
+        "bench" => bench(&flags),
+        "simulate" => simulate(&flags),
+        "export" => export(&flags),
+        "graph" => graph(&flags),
+        "raster" => raster(&flags),
+        "query" => query(&flags),
+        _ => Err(format!("unknown command {:?}", command)),
+    }
+}
+
+fn create(path: &str) -> Result<BufWriter<File>, String> {
+    File::create(path)
+        .map(BufWriter::new)
+        .map_err(|err| format!("failed to create {}: {}", path, err))
+}
+
+fn open(path: &str) -> Result<File, String> {
+    File::open(path).map_err(|err| format!("failed to open {}: {}", path, err))
+}
+
+fn generator_params(flags: &Flags) -> Result<GeneratorParams, String> {
+    let defaults = GeneratorParams::default();
+
+    let params = GeneratorParams {
+        node_count: flags.get("nodes", defaults.node_count)?,
+        min_dist: flags.get("min-dist", defaults.min_dist)?,
+        max_connect_dist: flags.get("connect-dist", defaults.max_connect_dist)?,
+        active_prob: flags.get("active-prob", defaults.active_prob)?,
+        gate_mix: [
+            flags.get("and", defaults.gate_mix[0])?,
+            flags.get("or", defaults.gate_mix[1])?,
+            flags.get("nor", defaults.gate_mix[2])?,
+        ],
+        topology: match flags.get("topology", "geometric".to_string())?.as_str() {
+            "geometric" => Topology::Geometric,
+            "kauffman" => Topology::Kauffman {
+                in_degree: flags.get("in-degree", 2)?,
+            },
+            name => return Err(format!("unknown topology {:?}", name)),
+        },
+    };
+
+    params.validate()?;
+    Ok(params)
+}
+
+fn ensemble_config(flags: &Flags) -> Result<EnsembleConfig, String> {
+    Ok(EnsembleConfig {
+        params: generator_params(flags)?,
+        networks: flags.get("networks", 16)?,
+        initial_states: flags.get("initial-states", 64)?,
+        max_steps: flags.get("max-steps", 10_000)?,
+        seed: flags.get("seed", 0)?,
+    })
+}
+
+fn ensemble(flags: &Flags) -> Result<(), String> {
+    let config = ensemble_config(flags)?;
+    let out = flags.get("out", "ensemble".to_string())?;
+
+    let ensemble = Ensemble::run(&config);
+    let summary = ensemble.summary();
+
+    ensemble
+        .write_csv(create(&format!("{}.csv", out))?)
+        .map_err(|err| err.to_string())?;
+    serde_json::to_writer_pretty(create(&format!("{}.json", out))?, &summary)
+        .map_err(|err| err.to_string())?;
+
+    println!(
+        "{} runs ({} unresolved): μ̃ = {}, λ̃ = {}, attractors ≈ {:.2}, P ≈ {:.2}",
+        ensemble.runs.len(),
+        summary.unresolved,
+        summary.mu.median,
+        summary.lambda.median,
+        summary.attractors.mean,
+        summary.p.mean
+    );
+
+    Ok(())
+}
+
+fn sweep(flags: &Flags) -> Result<(), String> {
+    let required = |key: &str| -> Result<String, String> {
+        flags
+            .0
+            .get(key)
+            .cloned()
+            .ok_or_else(|| format!("missing --{}", key))
+    };
+
+    let sweep = Sweep {
+        x: required("x")?.parse()?,
+        y: required("y")?.parse()?,
+        metric: flags
+            .get("metric", "cycle-length".to_string())?
+            .parse::<Metric>()?,
+        ensemble: ensemble_config(flags)?,
+    };
+    let out = flags.get("out", "sweep".to_string())?;
+
+    let grid = sweep.run()?;
+
+    sweep
+        .write_csv(&grid, create(&format!("{}.csv", out))?)
+        .map_err(|err| err.to_string())
+}
+
+fn bench(flags: &Flags) -> Result<(), String> {
+    let mut params = generator_params(flags)?;
+    params.node_count = flags.get("nodes", 100_000)?;
+    let in_degree = flags.get("in-degree", 2)?;
+    let steps = flags.get("steps", 20)?;
+
+    let mut rng = StdRng::seed_from_u64(flags.get("seed", 0)?);
+    let mut model = generate::kauffman(&params, in_degree, &mut rng);
+    let mut compiled = CompiledModel::new(&model);
+    let mut parallel = compiled.clone();
+
+    let time = |step: &mut dyn FnMut()| {
+        let start = Instant::now();
+        (0..steps).for_each(|_| step());
+        start.elapsed().as_secs_f64() * 1e3 / steps as f64
+    };
+
+    let reference_ms = time(&mut || {
+        model.step();
+    });
+    let compiled_ms = time(&mut || compiled.step());
+    let parallel_ms = time(&mut || parallel.par_step());
+
+    let mut check = model.clone();
+    compiled.write_to(&mut check);
+    let identical = model
+        .graph
+        .raw_nodes()
+        .iter()
+        .zip(check.graph.raw_nodes())
+        .all(|(a, b)| a.weight.kind.state() == b.weight.kind.state())
+        && (0..compiled.node_count()).all(|node| compiled.state(node) == parallel.state(node));
+
+    println!(
+        "{} nodes, {} steps: Model::step {:.3} ms, step {:.3} ms, par_step {:.3} ms ({})",
+        params.node_count,
+        steps,
+        reference_ms,
+        compiled_ms,
+        parallel_ms,
+        if identical { "identical" } else { "MISMATCH" }
+    );
+
+    Ok(())
+}
+
+/// A network being stepped, with its cycle search.
+struct Simulation {
+    params: GeneratorParams,
+    seed: u64,
+    model: Model,
+    cycle_finder: StreamingCycleFinder,
+    cycle: Option<Range<usize>>,
+    perturbed: bool,
+}
+
+impl Simulation {
+    fn new(params: GeneratorParams, seed: u64, model: Model) -> Self {
+        Self {
+            params,
+            seed,
+            cycle_finder: StreamingCycleFinder::new(&model),
+            model,
+            cycle: None,
+            perturbed: false,
+        }
+    }
+
+    /// Loads or generates a network, then lays it out with `--layout` if
+    /// given.
+    fn load_or_generate(flags: &Flags) -> Result<Self, String> {
+        let mut simulation = Self::load(flags)?;
+
+        if let Some(algorithm) = flags.0.get("layout") {
+            let spacing = flags.get("spacing", layout::SPACING)?;
+            let mut rng = StdRng::seed_from_u64(simulation.seed);
+            let positions =
+                Layout::new(algorithm.parse()?, &simulation.model, spacing, &mut rng).finish();
+
+            layout::apply(&mut simulation.model, &positions);
+        }
+
+        Ok(simulation)
+    }
+
+    /// Loads the snapshot given by `--in`, the GraphML topology given by
+    /// `--graphml` or the BoolNet rules given by `--bnet`, or else generates a
+    /// network.
+    fn load(flags: &Flags) -> Result<Self, String> {
+        if let Some(path) = flags.0.get("bnet") {
+            let src = std::fs::read_to_string(path)
+                .map_err(|err| format!("failed to read {}: {}", path, err))?;
+            let model = interchange::read_bnet(&src)?;
+
+            return Ok(Self::new(GeneratorParams::default(), 0, model));
+        }
+
+        if let Some(path) = flags.0.get("graphml") {
+            let src = std::fs::read_to_string(path)
+                .map_err(|err| format!("failed to read {}: {}", path, err))?;
+            let gate = flags.get("default-gate", "or".to_string())?;
+            let default_kind = interchange::parse_gate(&gate, false)
+                .ok_or_else(|| format!("unknown gate {:?}", gate))?;
+
+            let model = interchange::read_graphml(&src, default_kind)?;
+
+            return Ok(Self::new(GeneratorParams::default(), 0, model));
+        }
+
+        match flags.0.get("in") {
+            Some(path) => {
+                let snapshot = Snapshot::read(open(path)?)?;
+                let (model, _, cycle_finder) = snapshot.restore()?;
+
+                Ok(Self {
+                    params: snapshot.params,
+                    seed: snapshot.seed,
+                    model,
+                    cycle_finder,
+                    cycle: snapshot.cycle,
+                    perturbed: snapshot.perturbed,
+                })
+            }
+            None => {
+                let params = generator_params(flags)?;
+                let seed = flags.get("seed", 0)?;
+                let model = generate::build(&params, &mut StdRng::seed_from_u64(seed));
+
+                Ok(Self::new(params, seed, model))
+            }
+        }
+    }
+
+    fn advance(&mut self, steps: usize) {
+        for _ in 0..steps {
+            let state_hash = self.model.step();
+
+            if self.cycle.is_none() {
+                self.cycle = self.cycle_finder.check_next(state_hash);
+            }
+        }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot::new(
+            &self.params,
+            self.seed,
+            &self.model,
+            &self.model,
+            &self.cycle_finder,
+            &self.cycle,
+            self.perturbed,
+        )
+    }
+}
+
+/// Steps a generated (or, with `--in`, a saved) network and saves the result.
+fn simulate(flags: &Flags) -> Result<(), String> {
+    let steps = flags.get("steps", 1000)?;
+    let out = flags.get("out", "snapshot.json".to_string())?;
+
+    let mut simulation = Simulation::load_or_generate(flags)?;
+    simulation.advance(steps);
+    simulation.snapshot().write(create(&out)?)?;
+
+    let model = &simulation.model;
+    match &simulation.cycle {
+        Some(cycle) => println!(
+            "timestep {}: μ = {}, λ = {}, P ≈ {:.2}",
+            model.timestep,
+            cycle.start,
+            cycle.len(),
+            model.history.mean()
+        ),
+        None => println!(
+            "timestep {}: searching, P ≈ {:.2}",
+            model.timestep,
+            model.history.mean()
+        ),
+    }
+
+    Ok(())
+}
+
+/// Steps a generated (or saved) network and writes every node's states.
+fn export(flags: &Flags) -> Result<(), String> {
+    let format = flags.get("format", "wide".to_string())?.parse::<Format>()?;
+    let steps = flags.get("steps", 100)?;
+    let out = flags.get(
+        "out",
+        match format {
+            Format::Rle => "trajectory.rle",
+            Format::Long | Format::Wide => "trajectory.csv",
+        }
+        .to_string(),
+    )?;
+
+    let mut simulation = Simulation::load_or_generate(flags)?;
+    let window = simulation.model.history.window;
+    simulation.model.history = History::new(steps + 1, window).with_states();
+    simulation.advance(steps);
+
+    trajectory::write(format, &simulation.model, &simulation.cycle, create(&out)?)
+}
+
+/// Writes the graph of a generated (or loaded) network after `--steps` steps.
+fn graph(flags: &Flags) -> Result<(), String> {
+    let format = flags
+        .get("format", "graphml".to_string())?
+        .parse::<interchange::Format>()?;
+    let steps = flags.get("steps", 0)?;
+    let out = flags.get(
+        "out",
+        match format {
+            interchange::Format::GraphMl => "model.graphml",
+            interchange::Format::Gexf => "model.gexf",
+            interchange::Format::Dot => "model.dot",
+        }
+        .to_string(),
+    )?;
+
+    let mut simulation = Simulation::load_or_generate(flags)?;
+    simulation.advance(steps);
+
+    interchange::write(format, &simulation.model, create(&out)?).map_err(|err| err.to_string())
+}
+
+/// Plots the node states of a generated (or loaded) network over `--steps`
+/// steps.
+fn raster(flags: &Flags) -> Result<(), String> {
+    let format = flags
+        .get("format", "png".to_string())?
+        .parse::<raster::Format>()?;
+    let steps = flags.get("steps", 200)?;
+    let out = flags.get(
+        "out",
+        match format {
+            raster::Format::Png => "raster.png",
+            raster::Format::Svg => "raster.svg",
+        }
+        .to_string(),
+    )?;
+
+    let mut simulation = Simulation::load_or_generate(flags)?;
+    let window = simulation.model.history.window;
+    simulation.model.history = History::new(steps + 1, window).with_states();
+    simulation.advance(steps);
+
+    raster::Raster::new(&simulation.model, &simulation.cycle, steps + 1)?
+        .write(format, create(&out)?)
+}
+
+/// Checks the temporal-logic query `--formula` against every state of a
+/// generated (or loaded) network, or with `--current true` against its state
+/// after `--steps` steps only, printing a witness or counterexample.
+fn query(flags: &Flags) -> Result<(), String> {
+    let source = flags.0.get("formula").ok_or("missing --formula")?;
+    let current = flags.get("current", false)?;
+    let steps = flags.get("steps", 0)?;
+
+    let mut simulation = Simulation::load_or_generate(flags)?;
+    simulation.advance(steps);
+
+    let model = &simulation.model;
+    let query = Query::parse(model, source)?;
+    let verdict = if current {
+        query.check_current(model)
+    } else {
+        query.check(model)?
+    };
+
+    println!(
+        "{}",
+        if verdict.holds {
+            "holds"
+        } else {
+            "does not hold"
+        }
+    );
+
+    if let Some(trace) = &verdict.trace {
+        let names = model
+            .graph
+            .raw_nodes()
+            .iter()
+            .map(|node| node.weight.name.as_str())
+            .collect::<Vec<_>>();
+        println!("   {}", names.join(" "));
+
+        for (index, state) in trace.states.iter().enumerate() {
+            let marker = if index == trace.cycle_start {
+                "->"
+            } else {
+                "  "
+            };
+            let bits = state
+                .iter()
+                .zip(&names)
+                .map(|(&state, name)| format!("{:>width$}", state as u8, width = name.len()))
+                .collect::<Vec<_>>();
+
+            println!("{} {}", marker, bits.join(" "));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split(' ').map(String::from).collect()
+    }
+
+    #[test]
+    fn simulate_saves_layout() {
+        let path = std::env::temp_dir().join("boolean-network-layout.json");
+        let path = path.to_str().unwrap();
+
+        let flags = "--nodes 50 --seed 7 --layout layered --steps 20";
+        run(&args(&format!("simulate {} --out {}", flags, path))).unwrap();
+
+        let snapshot = Snapshot::read(open(path).unwrap()).unwrap();
+        let (compute, display, _) = snapshot.restore().unwrap();
+        let expected = Simulation::load_or_generate(&Flags::parse(&args(flags)).unwrap()).unwrap();
+
+        let positions = |model: &Model| {
+            model
+                .graph
+                .raw_nodes()
+                .iter()
+                .map(|node| node.weight.position)
+                .collect::<Vec<_>>()
+        };
+        let expected = positions(&expected.model);
+
+        assert_eq!(positions(&compute), expected);
+        assert_eq!(positions(&display), expected);
+        // The search restarts from nodes in the same place.
+        let saved = snapshot
+            .nodes
+            .iter()
+            .map(|node| node.position)
+            .collect::<Vec<_>>();
+        assert_eq!(saved, expected);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
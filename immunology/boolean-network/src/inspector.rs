@@ -0,0 +1,147 @@
+//! Panel describing a single node, selected by right-clicking it.
+
+use std::fmt::Write;
+
+use bevy::prelude::*;
+use petgraph::{graph::NodeIndex, EdgeDirection};
+
+use crate::{editor::Editor, model::NodeKind, picking, ModelState, NetworkConfig, Playback};
+
+#[derive(Default)]
+pub struct Inspector {
+    pub selected: Option<NodeIndex>,
+}
+
+pub struct InspectorText;
+
+/// Right-clicking a node selects it and right-clicking empty space clears the
+/// selection. In the editor, right-clicking deletes instead.
+#[allow(clippy::too_many_arguments)]
+pub fn select_node(
+    mouse: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    cameras: picking::Cameras,
+    interactions: Query<&Interaction>,
+    editor: Res<Editor>,
+    mut inspector: ResMut<Inspector>,
+    config: Res<NetworkConfig>,
+    model_opt: Option<Res<ModelState>>,
+) {
+    if editor.enabled || !mouse.just_pressed(MouseButton::Right) || picking::over_ui(&interactions)
+    {
+        return;
+    }
+
+    let model = if let Some(model) = model_opt {
+        model
+    } else {
+        return;
+    };
+
+    let selected = picking::cursor_ray(&windows, &cameras).and_then(|(origin, direction)| {
+        picking::pick_node(&model.display_model, origin, direction, config.bulb_radius)
+    });
+
+    if inspector.selected != selected {
+        inspector.selected = selected;
+    }
+}
+
+pub fn update_inspector(
+    mut inspector: ResMut<Inspector>,
+    playback: Res<Playback>,
+    model_opt: Option<Res<ModelState>>,
+    mut texts: Query<&mut Text, With<InspectorText>>,
+) {
+    let model = if let Some(model) = model_opt {
+        model
+    } else {
+        return;
+    };
+
+    let display_model = &model.display_model;
+
+    // Only cleared when gone, so that the inspector isn't marked changed.
+    if model.is_added()
+        && inspector.selected.map_or(false, |node| {
+            node.index() >= display_model.graph.node_count()
+        })
+    {
+        inspector.selected = None;
+    }
+
+    let text = match inspector.selected {
+        Some(node) => {
+            let rewound = playback
+                .cursor
+                .and_then(|timestep| display_model.history.states_at(timestep));
+            let state = |node: NodeIndex| match rewound {
+                Some(states) => states[node.index() / 64] >> (node.index() % 64) & 1 == 1,
+                None => display_model.graph[node].kind.state(),
+            };
+            let on_off = |state: bool| if state { "on" } else { "off" };
+
+            let weight = &display_model.graph[node];
+            let gate = match weight.kind {
+                NodeKind::And(_) => "and",
+                NodeKind::Or(_) => "or",
+                NodeKind::Nor(_) => "nor",
+            };
+
+            let mut text = format!("{} ({} gate", weight.name, gate);
+            if weight.pinned {
+                text += ", pinned";
+            }
+            writeln!(text, ")\nstate: {}", on_off(state(node))).unwrap();
+
+            for (label, direction) in &[
+                ("inputs", EdgeDirection::Incoming),
+                ("outputs", EdgeDirection::Outgoing),
+            ] {
+                let neighbors = display_model
+                    .graph
+                    .neighbors_directed(node, *direction)
+                    .map(|neighbor| {
+                        let name = &display_model.graph[neighbor].name;
+                        format!("{} {}", name, on_off(state(neighbor)))
+                    })
+                    .collect::<Vec<_>>();
+                writeln!(text, "{}: {}", label, neighbors.join(", ")).unwrap();
+            }
+
+            if let Some(activity) = display_model.history.activity(node.index()) {
+                writeln!(
+                    text,
+                    "activity: {:.2} over {} steps",
+                    activity,
+                    display_model
+                        .history
+                        .states()
+                        .map_or(0, |states| states.len())
+                )
+                .unwrap();
+            }
+
+            match (&model.cycle, &model.cycle_activity) {
+                (Some(cycle), Some(counts)) => {
+                    let count = counts[node.index()];
+                    if count == 0 || count == cycle.len() {
+                        write!(text, "cycle: frozen {}", on_off(count > 0)).unwrap();
+                    } else {
+                        write!(text, "cycle: active {} of {} steps", count, cycle.len()).unwrap();
+                    }
+                }
+                _ => text += "cycle: searching",
+            }
+
+            text
+        }
+        None => String::new(),
+    };
+
+    for mut inspector_text in texts.iter_mut() {
+        if inspector_text.sections[0].value != text {
+            inspector_text.sections[0].value = text.clone();
+        }
+    }
+}
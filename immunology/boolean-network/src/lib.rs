@@ -0,0 +1,1642 @@
+//! Simulation and 3D view of random Boolean networks, as a Bevy plugin.
+
+mod arrange;
+mod editor;
+mod generator;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod headless;
+mod inspector;
+pub mod model;
+mod overlay;
+mod picking;
+mod plots;
+mod pulses;
+#[cfg(target_arch = "wasm32")]
+mod session;
+mod storage;
+#[cfg(target_arch = "wasm32")]
+mod web;
+mod worker;
+
+use std::{
+    ops::{DerefMut, Range},
+    time::Duration,
+};
+
+use bevy::{ecs::system::EntityCommands, prelude::*, ui::FocusPolicy};
+use bevy_fly_camera::{FlyCamera, FlyCameraPlugin};
+
+pub use generator::GeneratorSettings;
+#[cfg(target_arch = "wasm32")]
+pub use worker::serve as serve_search_worker;
+
+use arrange::{ArrangeButton, ArrangeEvent, Arranger};
+use editor::{Editor, EditorButton, EditorEvent, EditorText};
+use generator::{PanelAction, PanelButton, PanelMaterials};
+use inspector::{Inspector, InspectorText};
+use model::{
+    edit::EditHistory,
+    generate::GeneratorParams,
+    history::{History, StreamingCycleFinder},
+    search::{self, Search},
+    snapshot::Snapshot,
+    *,
+};
+use overlay::{Overlay, OverlayButton, OverlayMaterials};
+use petgraph::visit::{EdgeRef, IntoNodeReferences};
+use pulses::{PulseAssets, PulseButton, Pulses};
+use rand::{
+    rngs::{OsRng, StdRng},
+    Rng, SeedableRng,
+};
+use worker::ComputeWorker;
+
+struct SimUpdateTimer(Timer);
+
+pub struct ModelState {
+    pub display_model: Model,
+    /// As last reported by the background search.
+    pub compute_model: Model,
+    pub cycle_finder: StreamingCycleFinder,
+    pub cycle: Option<Range<usize>>,
+    /// Per-node activity over the cycle, once found.
+    pub cycle_activity: Option<Vec<usize>>,
+    pub params: GeneratorParams,
+    pub seed: u64,
+    /// Whether node states were changed by hand since the network was made.
+    pub perturbed: bool,
+    pub edits: EditHistory,
+    /// Search stepping the compute model, started by `update_model`.
+    worker: Option<ComputeWorker>,
+}
+
+impl ModelState {
+    /// A simulation starting from `model`.
+    fn new(model: Model, params: GeneratorParams, seed: u64) -> Self {
+        let mut display_model = model.clone();
+        display_model.history = History::default().with_states();
+
+        Self {
+            display_model,
+            cycle_finder: StreamingCycleFinder::new(&model),
+            compute_model: model,
+            cycle: None,
+            cycle_activity: None,
+            params,
+            seed,
+            perturbed: false,
+            edits: Default::default(),
+            worker: None,
+        }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot::new(
+            &self.params,
+            self.seed,
+            &self.compute_model,
+            &self.display_model,
+            &self.cycle_finder,
+            &self.cycle,
+            self.perturbed,
+        )
+    }
+
+    /// The simulation saved in `snapshot`.
+    fn restore(snapshot: Snapshot) -> Result<Self, String> {
+        let (compute_model, mut display_model, cycle_finder) = snapshot.restore()?;
+
+        if display_model.history.states().is_none() {
+            display_model.history = std::mem::take(&mut display_model.history).with_states();
+        }
+
+        Ok(Self {
+            display_model,
+            compute_model,
+            cycle_finder,
+            cycle: snapshot.cycle,
+            cycle_activity: None,
+            params: snapshot.params,
+            seed: snapshot.seed,
+            perturbed: snapshot.perturbed,
+            edits: Default::default(),
+            worker: None,
+        })
+    }
+}
+
+pub struct Playback {
+    pub playing: bool,
+    /// Display steps per second.
+    pub speed: f32,
+    /// Timestep shown while rewound into the display model's history.
+    pub cursor: Option<usize>,
+    /// Whether to replay just the attractor once the cycle is known.
+    pub looping: bool,
+    /// Timestep (μ) the display model is being stepped to, a few steps a
+    /// frame.
+    pub seeking: Option<usize>,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self {
+            playing: true,
+            speed: 1.0,
+            cursor: None,
+            looping: false,
+            seeking: None,
+        }
+    }
+}
+
+struct SimNode {
+    pub graph_id: u32,
+    pub active: bool,
+    pub pinned: bool,
+}
+
+struct SimEdge {
+    pub graph_id: u32,
+    /// Length of the wire's mesh.
+    pub len: f32,
+}
+
+struct CycleText;
+struct ErrorText;
+struct PValueText;
+struct PlaybackText;
+
+struct MeshHandles {
+    pub bulb: Handle<Mesh>,
+    pub bulb_gate_indicator: Handle<Mesh>,
+    pub wire_director: Handle<Mesh>,
+}
+
+struct MaterialHandles {
+    pub bulb_inactive: Handle<StandardMaterial>,
+    pub bulb_active: Handle<StandardMaterial>,
+    pub bulb_pinned_inactive: Handle<StandardMaterial>,
+    pub bulb_pinned_active: Handle<StandardMaterial>,
+    pub gate_and: Handle<StandardMaterial>,
+    pub gate_or: Handle<StandardMaterial>,
+    pub gate_nor: Handle<StandardMaterial>,
+    pub wire: Handle<StandardMaterial>,
+}
+
+impl MaterialHandles {
+    fn bulb(&self, node: &SimNode) -> Handle<StandardMaterial> {
+        match (node.pinned, node.active) {
+            (false, false) => self.bulb_inactive.clone(),
+            (false, true) => self.bulb_active.clone(),
+            (true, false) => self.bulb_pinned_inactive.clone(),
+            (true, true) => self.bulb_pinned_active.clone(),
+        }
+    }
+}
+
+struct ButtonMaterials {
+    normal: Handle<ColorMaterial>,
+    hovered: Handle<ColorMaterial>,
+    pressed: Handle<ColorMaterial>,
+}
+
+impl FromWorld for ButtonMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+        ButtonMaterials {
+            normal: materials.add(Color::rgb(0.15, 0.15, 0.15).into()),
+            hovered: materials.add(Color::rgb(0.25, 0.25, 0.25).into()),
+            pressed: materials.add(Color::rgb(0.35, 0.75, 0.35).into()),
+        }
+    }
+}
+
+struct RegenerateButton;
+struct SaveButton;
+struct LoadButton;
+struct PlaybackButton(PlaybackEvent);
+struct SpeedSlider;
+struct SpeedSliderFill;
+
+/// Error shown to the user until it times out.
+#[derive(Default)]
+pub struct ErrorMessage(pub Option<String>);
+
+impl ErrorMessage {
+    /// Logs and shows `message`.
+    pub fn show(&mut self, message: String) {
+        error!("{}", message);
+        self.0 = Some(message);
+    }
+}
+
+pub struct RegenerateEvent(pub GeneratorSettings);
+pub struct SaveEvent;
+pub struct LoadEvent;
+
+#[derive(Clone, Copy)]
+pub enum PlaybackEvent {
+    Toggle,
+    StepForward,
+    StepBack,
+    JumpToCycle,
+    ToggleLoop,
+}
+
+const SNAPSHOT_PATH: &str = "snapshot.json";
+
+const ERROR_SECONDS: f32 = 10.0;
+
+const MIN_SPEED: f32 = 0.25;
+const MAX_SPEED: f32 = 16.0;
+
+/// Node updates per frame spent stepping the display model to μ.
+const SEEK_WORK: usize = 1 << 21;
+
+#[derive(Debug, Clone)]
+pub struct NetworkColors {
+    pub bulb_inactive: Color,
+    pub bulb_active: Color,
+    pub bulb_pinned_inactive: Color,
+    pub bulb_pinned_active: Color,
+    pub gate_and: Color,
+    pub gate_or: Color,
+    pub gate_nor: Color,
+    pub wire: Color,
+    pub pulse: Color,
+}
+
+impl Default for NetworkColors {
+    fn default() -> Self {
+        Self {
+            bulb_inactive: Color::rgba(0.8, 0.8, 0.95, 0.2),
+            bulb_active: Color::rgba(1.0, 0.86, 0.25, 0.5),
+            bulb_pinned_inactive: Color::rgba(0.45, 0.45, 0.95, 0.6),
+            bulb_pinned_active: Color::rgba(1.0, 0.5, 0.1, 0.8),
+            gate_and: Color::rgb(0.22, 0.95, 0.0),
+            gate_or: Color::rgb(0.0, 0.68, 0.95),
+            gate_nor: Color::rgb(0.95, 0.0, 0.22),
+            wire: Color::rgb(0.7, 0.7, 0.7),
+            pulse: Color::rgb(1.0, 0.95, 0.5),
+        }
+    }
+}
+
+/// Configuration of [`BooleanNetworkPlugin`], also available to its systems
+/// as a resource.
+#[derive(Debug, Clone)]
+pub struct NetworkConfig {
+    pub bulb_radius: f32,
+    /// Radius of the wires relative to that of the bulbs.
+    pub wire_radius_ratio: f32,
+    pub colors: NetworkColors,
+    /// Time between display steps at the initial playback speed.
+    pub step_interval: Duration,
+    /// Whether to spawn a light and a fly camera. Without them, nodes are
+    /// picked through the camera marked with [`NetworkCamera`].
+    pub spawn_camera: bool,
+    /// Whether to spawn the controls, texts and plots, with a UI camera.
+    pub spawn_ui: bool,
+    /// Whether to keep the generator settings and saved networks from one run
+    /// to the next, in the working directory (or the browser's storage on
+    /// the web), with Save and Load buttons.
+    pub persist: bool,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            bulb_radius: 1.0,
+            wire_radius_ratio: 0.05,
+            colors: Default::default(),
+            step_interval: Duration::from_millis(1000),
+            spawn_camera: true,
+            spawn_ui: true,
+            persist: false,
+        }
+    }
+}
+
+impl NetworkConfig {
+    /// Playback speed stepping once per [`step_interval`](Self::step_interval).
+    fn initial_speed(&self) -> f32 {
+        self.step_interval
+            .as_secs_f32()
+            .recip()
+            .clamp(MIN_SPEED, MAX_SPEED)
+    }
+}
+
+/// Marks the camera through which clicks pick nodes.
+pub struct NetworkCamera;
+
+/// Generates, steps and shows a Boolean network, with the controls around it
+/// unless [`NetworkConfig::spawn_ui`] is off.
+#[derive(Default)]
+pub struct BooleanNetworkPlugin {
+    pub config: NetworkConfig,
+}
+
+impl Plugin for BooleanNetworkPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let config = &self.config;
+
+        app.insert_resource(config.clone())
+            .add_event::<RegenerateEvent>()
+            .add_event::<SaveEvent>()
+            .add_event::<LoadEvent>()
+            .add_event::<PlaybackEvent>()
+            .add_event::<EditorEvent>()
+            .add_event::<ArrangeEvent>()
+            .init_resource::<ButtonMaterials>()
+            .init_resource::<PanelMaterials>()
+            .init_resource::<ErrorMessage>()
+            .insert_resource(if config.persist {
+                GeneratorSettings::load()
+            } else {
+                GeneratorSettings::default()
+            })
+            .insert_resource(Playback {
+                speed: config.initial_speed(),
+                ..Default::default()
+            })
+            .init_resource::<Editor>()
+            .init_resource::<Inspector>()
+            .init_resource::<Overlay>()
+            .init_resource::<Arranger>()
+            .init_resource::<Pulses>()
+            .init_resource::<PulseAssets>()
+            .init_resource::<OverlayMaterials>()
+            .insert_resource(SimUpdateTimer(Timer::new(config.step_interval, true)))
+            .add_startup_system(setup.system())
+            .add_system(generate_model.system())
+            .add_system(update_model.system())
+            .add_system(update_display.system())
+            .add_system(model_changed.system())
+            .add_system(node_changed.system())
+            .add_system(toggle_node.system())
+            .add_system(editor::editor_keys.system())
+            .add_system(editor::edit_graph.system())
+            .add_system(inspector::select_node.system())
+            .add_system(overlay::wire_overlay.system())
+            .add_system(arrange::arrange.system())
+            .add_system(pulses::spawn_pulses.system())
+            .add_system(pulses::animate_pulses.system());
+
+        if config.persist {
+            app.add_system(save_model.system())
+                .add_system(load_model.system());
+
+            if config.spawn_ui {
+                app.add_system(save_button.system())
+                    .add_system(load_button.system());
+            }
+        }
+
+        if config.spawn_camera {
+            app.add_plugin(FlyCameraPlugin)
+                .add_startup_system(setup_camera.system());
+        }
+
+        if config.spawn_ui {
+            app.add_startup_system(setup_ui.system())
+                .add_startup_system(plots::setup_plots.system())
+                .add_system(buttons.system())
+                .add_system(regenerate_button.system())
+                .add_system(playback_buttons.system())
+                .add_system(play_button_label.system())
+                .add_system(speed_slider.system())
+                .add_system(editor::editor_buttons.system())
+                .add_system(editor::editor_labels.system())
+                .add_system(inspector::update_inspector.system())
+                .add_system(plots::update_raster.system())
+                .add_system(plots::update_chart.system())
+                .add_system(overlay::overlay_button.system())
+                .add_system(overlay::overlay_button_label.system())
+                .add_system(arrange::arrange_buttons.system())
+                .add_system(arrange::arrange_labels.system())
+                .add_system(generator::panel_buttons.system())
+                .add_system(generator::panel_labels.system())
+                .add_system(pulses::pulse_button.system())
+                .add_system(pulses::pulse_button_label.system())
+                .add_system(error_text.system());
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            let linked = web::build(app);
+
+            if config.persist {
+                session::build(app, !linked);
+            }
+        }
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    config: Res<NetworkConfig>,
+    generator_settings: Res<GeneratorSettings>,
+    model_opt: Option<Res<ModelState>>,
+    mut regenerate_events: EventWriter<RegenerateEvent>,
+) {
+    // asset handles
+    let bulb_radius = config.bulb_radius;
+    let wire_radius = config.bulb_radius * config.wire_radius_ratio;
+
+    commands.insert_resource(MeshHandles {
+        bulb: meshes.add(
+            shape::Icosphere {
+                radius: bulb_radius,
+                subdivisions: 3,
+            }
+            .into(),
+        ),
+        bulb_gate_indicator: meshes.add(
+            shape::Torus {
+                radius: bulb_radius * 0.25,
+                ring_radius: bulb_radius * 0.15,
+                ..Default::default()
+            }
+            .into(),
+        ),
+        wire_director: meshes.add(
+            shape::Torus {
+                radius: wire_radius * 3.0,
+                ring_radius: wire_radius * 2.0,
+                ..Default::default()
+            }
+            .into(),
+        ),
+    });
+
+    let colors = &config.colors;
+
+    commands.insert_resource(MaterialHandles {
+        bulb_inactive: materials.add(colors.bulb_inactive.into()),
+        bulb_active: materials.add(colors.bulb_active.into()),
+        bulb_pinned_inactive: materials.add(colors.bulb_pinned_inactive.into()),
+        bulb_pinned_active: materials.add(colors.bulb_pinned_active.into()),
+        gate_and: materials.add(colors.gate_and.into()),
+        gate_or: materials.add(colors.gate_or.into()),
+        gate_nor: materials.add(colors.gate_nor.into()),
+        wire: materials.add(colors.wire.into()),
+    });
+
+    // sim model, unless restored
+    if model_opt.is_none() {
+        regenerate_events.send(RegenerateEvent(generator_settings.clone()));
+    }
+}
+
+fn setup_camera(mut commands: Commands) {
+    // light
+    commands.spawn_bundle(LightBundle {
+        light: Light {
+            range: 10000.0,
+            // depth: 0.1..100.0,
+            intensity: 5000.0,
+            ..Default::default()
+        },
+        transform: Transform::from_translation(Vec3::new(0.0, 20.0, 0.0)),
+        ..Default::default()
+    });
+    // camera
+    commands
+        .spawn()
+        .insert_bundle(PerspectiveCameraBundle {
+            transform: Transform::from_translation(Vec3::new(-2.0, 2.5, 5.0))
+                .looking_at(Vec3::default(), Vec3::Y),
+            ..Default::default()
+        })
+        .insert(FlyCamera::default())
+        .insert(NetworkCamera);
+}
+
+fn setup_ui(
+    mut commands: Commands,
+    button_materials: Res<ButtonMaterials>,
+    asset_server: Res<AssetServer>,
+    config: Res<NetworkConfig>,
+) {
+    commands.spawn_bundle(UiCameraBundle::default());
+
+    // ui elements
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                align_self: AlignSelf::FlexEnd,
+                margin: Rect {
+                    right: Val::Auto,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text {
+                sections: vec![
+                    TextSection {
+                        value: "Cycle: ".to_string(),
+                        style: TextStyle {
+                            font: asset_server.load("fonts/FiraCode-Bold.ttf"),
+                            font_size: 60.0,
+                            color: Color::WHITE,
+                        },
+                    },
+                    TextSection {
+                        value: "".to_string(),
+                        style: TextStyle {
+                            font: asset_server.load("fonts/FiraCode-Medium.ttf"),
+                            font_size: 60.0,
+                            color: Color::GOLD,
+                        },
+                    },
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(CycleText);
+
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                align_self: AlignSelf::FlexEnd,
+                ..Default::default()
+            },
+            text: Text {
+                sections: vec![
+                    TextSection {
+                        value: "P ≈ ".to_string(),
+                        style: TextStyle {
+                            font: asset_server.load("fonts/FiraCode-Bold.ttf"),
+                            font_size: 60.0,
+                            color: Color::WHITE,
+                        },
+                    },
+                    TextSection {
+                        value: "".to_string(),
+                        style: TextStyle {
+                            font: asset_server.load("fonts/FiraCode-Medium.ttf"),
+                            font_size: 60.0,
+                            color: Color::GOLD,
+                        },
+                    },
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(PValueText);
+
+    spawn_button(
+        &mut commands,
+        &button_materials,
+        &asset_server,
+        "Regenerate Model",
+        0.0,
+        0.0,
+        300.0,
+    )
+    .insert(RegenerateButton);
+
+    if config.persist {
+        spawn_button(
+            &mut commands,
+            &button_materials,
+            &asset_server,
+            "Save",
+            310.0,
+            0.0,
+            140.0,
+        )
+        .insert(SaveButton);
+
+        spawn_button(
+            &mut commands,
+            &button_materials,
+            &asset_server,
+            "Load",
+            460.0,
+            0.0,
+            140.0,
+        )
+        .insert(LoadButton);
+    }
+
+    spawn_button(
+        &mut commands,
+        &button_materials,
+        &asset_server,
+        "Params",
+        610.0,
+        0.0,
+        160.0,
+    )
+    .insert(PanelButton(PanelAction::Toggle));
+
+    // playback controls
+    spawn_button(
+        &mut commands,
+        &button_materials,
+        &asset_server,
+        "Pause",
+        0.0,
+        75.0,
+        140.0,
+    )
+    .insert(PlaybackButton(PlaybackEvent::Toggle));
+
+    for (label, left, width, event) in [
+        ("Back", 150.0, 140.0, PlaybackEvent::StepBack),
+        ("Step", 300.0, 140.0, PlaybackEvent::StepForward),
+        ("Jump to μ", 450.0, 200.0, PlaybackEvent::JumpToCycle),
+    ] {
+        spawn_button(
+            &mut commands,
+            &button_materials,
+            &asset_server,
+            label,
+            left,
+            75.0,
+            width,
+        )
+        .insert(PlaybackButton(event));
+    }
+
+    spawn_button(
+        &mut commands,
+        &button_materials,
+        &asset_server,
+        "",
+        660.0,
+        75.0,
+        300.0,
+    )
+    .insert(SpeedSlider)
+    .with_children(|parent| {
+        parent
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(0.0), Val::Percent(100.0)),
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        left: Val::Px(0.0),
+                        bottom: Val::Px(0.0),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                material: button_materials.pressed.clone(),
+                ..Default::default()
+            })
+            .insert(FocusPolicy::Pass)
+            .insert(SpeedSliderFill);
+    });
+
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(970.0),
+                    bottom: Val::Px(85.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraCode-Medium.ttf"),
+                    font_size: 40.0,
+                    color: Color::WHITE,
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(PlaybackText);
+
+    // editor controls
+    for (label, left, event) in [
+        ("Edit", 0.0, EditorEvent::Toggle),
+        ("Undo", 150.0, EditorEvent::Undo),
+        ("Redo", 300.0, EditorEvent::Redo),
+    ] {
+        spawn_button(
+            &mut commands,
+            &button_materials,
+            &asset_server,
+            label,
+            left,
+            150.0,
+            140.0,
+        )
+        .insert(EditorButton(event));
+    }
+
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(450.0),
+                    bottom: Val::Px(160.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraCode-Medium.ttf"),
+                    font_size: 40.0,
+                    color: Color::WHITE,
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(EditorText);
+
+    // attractor overlay
+    spawn_button(
+        &mut commands,
+        &button_materials,
+        &asset_server,
+        "Heat: off",
+        0.0,
+        225.0,
+        200.0,
+    )
+    .insert(OverlayButton);
+
+    spawn_button(
+        &mut commands,
+        &button_materials,
+        &asset_server,
+        "Loop: off",
+        210.0,
+        225.0,
+        200.0,
+    )
+    .insert(PlaybackButton(PlaybackEvent::ToggleLoop));
+
+    spawn_button(
+        &mut commands,
+        &button_materials,
+        &asset_server,
+        "Pulses: on",
+        420.0,
+        225.0,
+        220.0,
+    )
+    .insert(PulseButton);
+
+    // layout controls
+    for (label, left, width, event) in [
+        (
+            "Force",
+            0.0,
+            140.0,
+            ArrangeEvent::Run(layout::Algorithm::Force),
+        ),
+        (
+            "Force 3D",
+            150.0,
+            160.0,
+            ArrangeEvent::Run(layout::Algorithm::Force3d),
+        ),
+        (
+            "Layered",
+            320.0,
+            160.0,
+            ArrangeEvent::Run(layout::Algorithm::Layered),
+        ),
+        ("Animate: on", 490.0, 220.0, ArrangeEvent::ToggleAnimate),
+    ] {
+        spawn_button(
+            &mut commands,
+            &button_materials,
+            &asset_server,
+            label,
+            left,
+            300.0,
+            width,
+        )
+        .insert(ArrangeButton(event));
+    }
+
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(10.0),
+                    top: Val::Px(10.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraCode-Medium.ttf"),
+                    font_size: 32.0,
+                    color: Color::WHITE,
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(InspectorText);
+
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(0.0),
+                    bottom: Val::Px(375.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraCode-Medium.ttf"),
+                    font_size: 32.0,
+                    color: Color::rgb(1.0, 0.35, 0.3),
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        })
+        .insert(ErrorText);
+}
+fn spawn_button<'a, 'b>(
+    commands: &'b mut Commands<'a>,
+    button_materials: &ButtonMaterials,
+    asset_server: &AssetServer,
+    label: &str,
+    left: f32,
+    bottom: f32,
+    width: f32,
+) -> EntityCommands<'a, 'b> {
+    let mut button = commands.spawn_bundle(ButtonBundle {
+        style: Style {
+            size: Size::new(Val::Px(width), Val::Px(65.0)),
+            position_type: PositionType::Absolute,
+            position: Rect {
+                left: Val::Px(left),
+                right: Val::Auto,
+                top: Val::Auto,
+                bottom: Val::Px(bottom),
+            },
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        },
+        material: button_materials.normal.clone(),
+        ..Default::default()
+    });
+
+    button.with_children(|parent| {
+        parent.spawn_bundle(TextBundle {
+            text: Text::with_section(
+                label,
+                TextStyle {
+                    font: asset_server.load("fonts/NotoSans-Bold.ttf"),
+                    font_size: 40.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                },
+                Default::default(),
+            ),
+            ..Default::default()
+        });
+    });
+
+    button
+}
+
+fn generate_model(
+    mut commands: Commands,
+    mut events: EventReader<RegenerateEvent>,
+    config: Res<NetworkConfig>,
+) {
+    let settings = if let Some(RegenerateEvent(settings)) = events.iter().last() {
+        settings
+    } else {
+        return;
+    };
+
+    if config.persist {
+        settings.save();
+    }
+
+    let params = settings.params.clone();
+    let seed = settings.seed.unwrap_or_else(|| OsRng.gen());
+    let model = generate::build(&params, &mut StdRng::seed_from_u64(seed));
+
+    replace_model(&mut commands, ModelState::new(model, params, seed));
+}
+
+fn replace_model(commands: &mut Commands, model: ModelState) {
+    // Removing the old state first makes the new one count as added, which is
+    // what `model_changed` rebuilds the scene on.
+    commands.remove_resource::<ModelState>();
+    commands.insert_resource(model);
+}
+
+fn save_model(
+    mut events: EventReader<SaveEvent>,
+    model_opt: Option<Res<ModelState>>,
+    mut error_message: ResMut<ErrorMessage>,
+) {
+    if events.iter().count() == 0 {
+        return;
+    }
+
+    let model = if let Some(model) = model_opt {
+        model
+    } else {
+        return;
+    };
+
+    match store_snapshot(SNAPSHOT_PATH, &model.snapshot()) {
+        Ok(()) => info!("saved {}", SNAPSHOT_PATH),
+        Err(err) => error_message.show(format!("failed to save {}: {}", SNAPSHOT_PATH, err)),
+    }
+}
+
+fn store_snapshot(name: &str, snapshot: &Snapshot) -> Result<(), String> {
+    let mut json = Vec::new();
+    snapshot.write(&mut json)?;
+
+    storage::write(
+        name,
+        &String::from_utf8(json).map_err(|err| err.to_string())?,
+    )
+}
+
+fn stored_snapshot(name: &str) -> Result<Option<Snapshot>, String> {
+    storage::read(name)?
+        .map(|json| Snapshot::read(json.as_bytes()))
+        .transpose()
+}
+
+fn load_model(
+    mut commands: Commands,
+    mut events: EventReader<LoadEvent>,
+    mut error_message: ResMut<ErrorMessage>,
+) {
+    if events.iter().count() == 0 {
+        return;
+    }
+
+    let result = stored_snapshot(SNAPSHOT_PATH)
+        .and_then(|snapshot| snapshot.ok_or_else(|| "nothing saved yet".to_string()))
+        .and_then(ModelState::restore);
+
+    match result {
+        Ok(model) => replace_model(&mut commands, model),
+        Err(err) => error_message.show(format!("failed to load {}: {}", SNAPSHOT_PATH, err)),
+    }
+}
+
+fn update_model(
+    model_opt: Option<ResMut<ModelState>>,
+    mut error_message: ResMut<ErrorMessage>,
+    mut cycle_text: Query<&mut Text, (With<CycleText>, Without<PValueText>)>,
+    mut pvalue_text: Query<&mut Text, (With<PValueText>, Without<CycleText>)>,
+) {
+    let mut model = if let Some(model) = model_opt {
+        model
+    } else {
+        return;
+    };
+
+    // Only borrowed mutably to start the search or take its progress, as
+    // systems watching the model for changes would otherwise run every frame.
+    if model.worker.is_none() {
+        let search = Search::new(
+            model.compute_model.clone(),
+            model.cycle_finder.clone(),
+            model.cycle.clone(),
+            search::DEFAULT_BUDGET,
+        );
+        model.worker = Some(ComputeWorker::spawn(search));
+    }
+
+    let polled = model.worker.as_ref().unwrap().poll();
+
+    match polled {
+        Ok(Some(search)) => {
+            let model = model.deref_mut();
+            model.compute_model = search.model;
+            model.cycle_finder = search.cycle_finder;
+            model.cycle = search.cycle;
+            model.cycle_activity = search.cycle_activity;
+        }
+        Ok(None) => {}
+        Err(err) => error_message.show(format!("the cycle search failed: {}", err)),
+    }
+
+    let ModelState {
+        compute_model,
+        cycle_finder,
+        cycle,
+        perturbed,
+        worker,
+        ..
+    } = &*model;
+    let worker = worker.as_ref().unwrap();
+
+    for mut text in cycle_text.iter_mut() {
+        text.sections[1].value = match cycle {
+            Some(cycle_range) => format!("μ = {}, λ = {}", cycle_range.start, cycle_range.len()),
+            None if worker.is_finished() => format!(
+                "none within {} steps",
+                compute_model.timestep - cycle_finder.start()
+            ),
+            None => format!("searching (steps = {})", compute_model.timestep),
+        };
+
+        if *perturbed {
+            text.sections[1].value += &format!(" (perturbed at t = {})", cycle_finder.start());
+        }
+    }
+
+    for mut text in pvalue_text.iter_mut() {
+        text.sections[1].value = format!("{:.2}", compute_model.history.mean());
+    }
+}
+
+fn step_forward(display_model: &mut Model, playback: &mut Playback, cycle: &Option<Range<usize>>) {
+    if let (true, Some(range)) = (playback.looping, cycle) {
+        let timestep = playback.cursor.unwrap_or(display_model.timestep);
+
+        // The state at μ + λ is the one at μ again.
+        if (timestep < range.start || timestep + 1 >= range.end)
+            && (range.start >= display_model.timestep
+                || display_model.history.states_at(range.start).is_some())
+        {
+            jump_to_cycle(display_model, playback, cycle);
+            return;
+        }
+    }
+
+    playback.seeking = None;
+    playback.cursor = match playback.cursor {
+        Some(timestep) if timestep + 1 < display_model.timestep => Some(timestep + 1),
+        Some(_) => None,
+        None => {
+            display_model.step();
+            None
+        }
+    };
+}
+
+fn step_back(display_model: &Model, playback: &mut Playback) {
+    let timestep = playback.cursor.unwrap_or(display_model.timestep);
+
+    if timestep > 0 && display_model.history.states_at(timestep - 1).is_some() {
+        playback.seeking = None;
+        playback.cursor = Some(timestep - 1);
+    }
+}
+
+fn jump_to_cycle(display_model: &mut Model, playback: &mut Playback, cycle: &Option<Range<usize>>) {
+    let mu = if let Some(cycle) = cycle {
+        cycle.start
+    } else {
+        return;
+    };
+
+    // μ may be up to a whole search budget ahead, so `update_display` gets
+    // there over several frames.
+    if mu >= display_model.timestep {
+        playback.seeking = Some(mu);
+        playback.cursor = None;
+    } else if display_model.history.states_at(mu).is_some() {
+        playback.seeking = None;
+        playback.cursor = Some(mu);
+    } else {
+        warn!("timestep {} is no longer in the history", mu);
+    }
+}
+
+fn update_display(
+    time: Res<Time>,
+    mut timer: ResMut<SimUpdateTimer>,
+    mut playback: ResMut<Playback>,
+    mut events: EventReader<PlaybackEvent>,
+    model_opt: Option<ResMut<ModelState>>,
+    mut nodes: Query<&mut SimNode>,
+    mut playback_text: Query<&mut Text, With<PlaybackText>>,
+) {
+    let mut model = if let Some(model) = model_opt {
+        model
+    } else {
+        return;
+    };
+
+    // A regenerated or loaded model starts out live.
+    if let Some(timestep) = playback.cursor {
+        let display_model = &model.display_model;
+
+        if timestep >= display_model.timestep || display_model.history.states_at(timestep).is_none()
+        {
+            playback.cursor = None;
+        }
+    }
+
+    // Both resources are only borrowed mutably when stepping, so that systems
+    // watching them for changes don't run every frame.
+    let mut stepped = false;
+    let mut events = events.iter().peekable();
+
+    if events.peek().is_some() {
+        let ModelState {
+            display_model,
+            cycle,
+            ..
+        } = model.deref_mut();
+        let playback = playback.deref_mut();
+
+        for event in events {
+            match event {
+                PlaybackEvent::Toggle => playback.playing = !playback.playing,
+                PlaybackEvent::StepForward => step_forward(display_model, playback, cycle),
+                PlaybackEvent::StepBack => step_back(display_model, playback),
+                PlaybackEvent::JumpToCycle => jump_to_cycle(display_model, playback, cycle),
+                PlaybackEvent::ToggleLoop => playback.looping = !playback.looping,
+            }
+        }
+
+        stepped = true;
+    }
+
+    // A seek is abandoned along with the cycle it was heading for.
+    if let Some(mu) = playback.seeking {
+        if model.cycle.as_ref().map(|cycle| cycle.start) != Some(mu) {
+            playback.seeking = None;
+        }
+    }
+
+    if let Some(mu) = playback.seeking {
+        let display_model = &mut model.display_model;
+        let chunk = SEEK_WORK / display_model.graph.node_count().max(1);
+
+        for _ in 0..chunk.max(1).min(mu.saturating_sub(display_model.timestep)) {
+            display_model.step();
+        }
+
+        if display_model.timestep >= mu {
+            playback.seeking = None;
+        }
+        stepped = true;
+    }
+
+    timer
+        .0
+        .set_duration(Duration::from_secs_f32(playback.speed.recip()));
+
+    if playback.playing && playback.seeking.is_none() && timer.0.tick(time.delta()).just_finished()
+    {
+        let ModelState {
+            display_model,
+            cycle,
+            ..
+        } = model.deref_mut();
+
+        step_forward(display_model, playback.deref_mut(), cycle);
+        stepped = true;
+    }
+
+    let display_model = &model.display_model;
+
+    if stepped {
+        let rewound = playback
+            .cursor
+            .and_then(|timestep| display_model.history.states_at(timestep));
+
+        let node_count = display_model.graph.node_count();
+
+        // Nodes of a replaced network linger until `model_changed` despawns
+        // them.
+        for mut state in nodes
+            .iter_mut()
+            .filter(|state| (state.graph_id as usize) < node_count)
+        {
+            state.active = match rewound {
+                Some(states) => {
+                    states[state.graph_id as usize / 64] >> (state.graph_id % 64) & 1 == 1
+                }
+                None => display_model
+                    .graph
+                    .node_weight(state.graph_id.into())
+                    .unwrap()
+                    .kind
+                    .state(),
+            };
+        }
+    }
+
+    let value = match (playback.cursor, playback.seeking) {
+        (Some(timestep), _) => {
+            format!("t = {} (rewound), {:.2} steps/s", timestep, playback.speed)
+        }
+        (None, Some(mu)) => format!("t = {} (stepping to μ = {})", display_model.timestep, mu),
+        (None, None) => format!(
+            "t = {}, {:.2} steps/s",
+            display_model.timestep, playback.speed
+        ),
+    };
+
+    for mut text in playback_text.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn model_changed(
+    mut commands: Commands,
+    model_opt: Option<Res<ModelState>>,
+    nodes: Query<Entity, With<SimNode>>,
+    edges: Query<(Entity, &Handle<Mesh>), With<SimEdge>>,
+    mesh_handles: Res<MeshHandles>,
+    material_handles: Res<MaterialHandles>,
+    config: Res<NetworkConfig>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let model = if let Some(model) = model_opt {
+        model
+    } else {
+        return;
+    };
+
+    if !model.is_added() {
+        return;
+    }
+
+    for entity in nodes.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    for (entity, mesh_handle) in edges.iter() {
+        commands.entity(entity).despawn_recursive();
+
+        meshes.remove(mesh_handle).unwrap();
+    }
+
+    let display_model = &model.display_model;
+
+    for (node_id, weight) in display_model.graph.node_references() {
+        let graph_id = node_id.index() as u32;
+        let node = SimNode {
+            graph_id,
+            active: weight.kind.state(),
+            pinned: weight.pinned,
+        };
+
+        commands
+            .spawn()
+            .insert_bundle(PbrBundle {
+                mesh: mesh_handles.bulb.clone(),
+                material: material_handles.bulb(&node),
+                transform: Transform::from_translation(weight.position),
+                ..Default::default()
+            })
+            .insert(node)
+            .with_children(|parent| {
+                parent.spawn_bundle(PbrBundle {
+                    mesh: mesh_handles.bulb_gate_indicator.clone(),
+                    material: match weight.kind {
+                        NodeKind::And(_) => material_handles.gate_and.clone(),
+                        NodeKind::Or(_) => material_handles.gate_or.clone(),
+                        NodeKind::Nor(_) => material_handles.gate_nor.clone(),
+                    },
+                    transform: Transform::from_translation(Vec3::Y * config.bulb_radius * 1.1),
+                    ..Default::default()
+                });
+            });
+    }
+
+    for edge in display_model.graph.edge_references() {
+        let graph_id = edge.id().index() as u32;
+        let (source_weight, target_weight) = (
+            display_model.graph.node_weight(edge.source()).unwrap(),
+            display_model.graph.node_weight(edge.target()).unwrap(),
+        );
+
+        let (wire_transform, len) = wire_placement(
+            source_weight.position,
+            target_weight.position,
+            config.bulb_radius,
+        );
+
+        let mesh = shape::Capsule {
+            radius: config.bulb_radius * config.wire_radius_ratio,
+            depth: len,
+            ..Default::default()
+        };
+
+        let director_transform = Transform::from_translation(Vec3::Y * len * 0.45);
+
+        let director = commands
+            .spawn()
+            .insert_bundle(PbrBundle {
+                mesh: mesh_handles.wire_director.clone(),
+                material: material_handles.wire.clone(),
+                transform: director_transform,
+                ..Default::default()
+            })
+            .id();
+
+        commands
+            .spawn()
+            .insert(SimEdge { graph_id, len })
+            .insert_bundle(PbrBundle {
+                mesh: meshes.add(mesh.into()),
+                material: material_handles.wire.clone(),
+                transform: wire_transform,
+                ..Default::default()
+            })
+            .push_children(&[director]);
+    }
+}
+
+/// Transform of a wire between bulbs at the given positions, and its length.
+fn wire_placement(source_pos: Vec3, target_pos: Vec3, bulb_radius: f32) -> (Transform, f32) {
+    let len = source_pos.distance(target_pos) - (bulb_radius * 2.0);
+    let midpoint = (source_pos + target_pos) * 0.5;
+    let dir_vec = target_pos - source_pos;
+
+    let rotation = Quat::from_rotation_arc_colinear(Vec3::Y, dir_vec.normalize());
+
+    let mut wire_transform = Transform::from_translation(midpoint);
+    wire_transform.rotate(rotation);
+
+    (wire_transform, len)
+}
+
+fn node_changed(
+    mut nodes: Query<(&SimNode, &mut Handle<StandardMaterial>, &mut Transform)>,
+    materials: Res<MaterialHandles>,
+    overlay_materials: Res<OverlayMaterials>,
+    overlay: Res<Overlay>,
+    model_opt: Option<Res<ModelState>>,
+) {
+    let attractor = model_opt
+        .as_ref()
+        .and_then(|model| overlay::attractor(&overlay, model));
+
+    for (node, mut material, mut transform) in nodes.iter_mut() {
+        let count = attractor
+            .and_then(|(period, counts)| Some((period, *counts.get(node.graph_id as usize)?)));
+
+        let (bulb, scale) = match count {
+            Some((period, count)) => overlay_materials.bulb(count, period),
+            None => (materials.bulb(node), 1.0),
+        };
+
+        *material = bulb;
+
+        if transform.scale != Vec3::splat(scale) {
+            transform.scale = Vec3::splat(scale);
+        }
+    }
+}
+
+/// Click flips the state of the node under the cursor and shift-click pins
+/// it. The cycle search restarts from the perturbed state.
+#[allow(clippy::too_many_arguments)]
+fn toggle_node(
+    mouse: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    windows: Res<Windows>,
+    cameras: picking::Cameras,
+    interactions: Query<&Interaction>,
+    editor: Res<Editor>,
+    mut nodes: Query<&mut SimNode>,
+    mut playback: ResMut<Playback>,
+    config: Res<NetworkConfig>,
+    model_opt: Option<ResMut<ModelState>>,
+) {
+    if editor.enabled || !mouse.just_pressed(MouseButton::Left) || picking::over_ui(&interactions) {
+        return;
+    }
+
+    let mut model = if let Some(model) = model_opt {
+        model
+    } else {
+        return;
+    };
+
+    let node = picking::cursor_ray(&windows, &cameras).and_then(|(origin, direction)| {
+        picking::pick_node(&model.display_model, origin, direction, config.bulb_radius)
+    });
+
+    let node = if let Some(node) = node {
+        node
+    } else {
+        return;
+    };
+
+    let ModelState {
+        display_model,
+        compute_model,
+        cycle_finder,
+        cycle,
+        cycle_activity,
+        perturbed,
+        worker,
+        ..
+    } = model.deref_mut();
+
+    // Editing a rewound state discards the history after it.
+    if let Some(timestep) = playback.cursor.take() {
+        if let Err(err) = display_model.rewind(timestep) {
+            warn!("failed to rewind to {}: {}", timestep, err);
+            return;
+        }
+    }
+
+    let weight = display_model.graph.node_weight_mut(node).unwrap();
+
+    if keys.pressed(KeyCode::LShift) || keys.pressed(KeyCode::RShift) {
+        weight.pinned = !weight.pinned;
+    } else {
+        weight.kind = weight.kind.with_state(!weight.kind.state());
+    }
+
+    display_model.amend();
+
+    *compute_model = display_model.clone();
+    compute_model.history = History::default();
+    *cycle_finder = StreamingCycleFinder::new(display_model);
+    *cycle = None;
+    *cycle_activity = None;
+    *perturbed = true;
+    *worker = None;
+
+    for mut node in nodes.iter_mut() {
+        if let Some(weight) = display_model.graph.node_weight(node.graph_id.into()) {
+            node.active = weight.kind.state();
+            node.pinned = weight.pinned;
+        }
+    }
+}
+
+fn buttons(
+    button_materials: Res<ButtonMaterials>,
+    mut interaction_query: Query<
+        (&Interaction, &mut Handle<ColorMaterial>),
+        (Changed<Interaction>, With<Button>),
+    >,
+) {
+    for (interaction, mut material) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                *material = button_materials.pressed.clone();
+            }
+            Interaction::Hovered => {
+                *material = button_materials.hovered.clone();
+            }
+            Interaction::None => {
+                *material = button_materials.normal.clone();
+            }
+        }
+    }
+}
+
+fn regenerate_button(
+    interactions: Query<&Interaction, (Changed<Interaction>, With<Button>, With<RegenerateButton>)>,
+    settings: Res<GeneratorSettings>,
+    mut events: EventWriter<RegenerateEvent>,
+) {
+    for interaction in interactions.iter() {
+        if let Interaction::Clicked = interaction {
+            events.send(RegenerateEvent(settings.clone()));
+        }
+    }
+}
+
+fn save_button(
+    interactions: Query<&Interaction, (Changed<Interaction>, With<Button>, With<SaveButton>)>,
+    mut events: EventWriter<SaveEvent>,
+) {
+    for interaction in interactions.iter() {
+        if let Interaction::Clicked = interaction {
+            events.send(SaveEvent);
+        }
+    }
+}
+
+fn load_button(
+    interactions: Query<&Interaction, (Changed<Interaction>, With<Button>, With<LoadButton>)>,
+    mut events: EventWriter<LoadEvent>,
+) {
+    for interaction in interactions.iter() {
+        if let Interaction::Clicked = interaction {
+            events.send(LoadEvent);
+        }
+    }
+}
+
+fn playback_buttons(
+    interactions: Query<(&Interaction, &PlaybackButton), (Changed<Interaction>, With<Button>)>,
+    mut events: EventWriter<PlaybackEvent>,
+) {
+    for (interaction, button) in interactions.iter() {
+        if let Interaction::Clicked = interaction {
+            events.send(button.0);
+        }
+    }
+}
+
+fn play_button_label(
+    playback: Res<Playback>,
+    buttons: Query<(&PlaybackButton, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    if !playback.is_changed() {
+        return;
+    }
+
+    for (button, children) in buttons.iter() {
+        let label = match button.0 {
+            PlaybackEvent::Toggle if playback.playing => "Pause",
+            PlaybackEvent::Toggle => "Play",
+            PlaybackEvent::ToggleLoop if playback.looping => "Loop: on",
+            PlaybackEvent::ToggleLoop => "Loop: off",
+            _ => continue,
+        };
+
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value = label.to_string();
+            }
+        }
+    }
+}
+
+fn speed_slider(
+    windows: Res<Windows>,
+    mut playback: ResMut<Playback>,
+    sliders: Query<(&Interaction, &Node, &GlobalTransform), With<SpeedSlider>>,
+    mut fills: Query<&mut Style, With<SpeedSliderFill>>,
+) {
+    let cursor = windows
+        .get_primary()
+        .and_then(|window| window.cursor_position());
+
+    for (interaction, node, transform) in sliders.iter() {
+        if let (Interaction::Clicked, Some(cursor)) = (interaction, cursor) {
+            let left = transform.translation.x - node.size.x / 2.0;
+            let fraction = ((cursor.x - left) / node.size.x).clamp(0.0, 1.0);
+
+            // Logarithmic, so that every doubling takes the same distance.
+            let speed = MIN_SPEED * (MAX_SPEED / MIN_SPEED).powf(fraction);
+            if (speed - playback.speed).abs() > f32::EPSILON {
+                playback.speed = speed;
+            }
+        }
+    }
+
+    if playback.is_changed() {
+        let fraction = (playback.speed / MIN_SPEED).ln() / (MAX_SPEED / MIN_SPEED).ln();
+
+        for mut style in fills.iter_mut() {
+            style.size.width = Val::Percent(fraction * 100.0);
+        }
+    }
+}
+
+fn error_text(
+    time: Res<Time>,
+    mut error_message: ResMut<ErrorMessage>,
+    mut shown_for: Local<f32>,
+    mut texts: Query<&mut Text, With<ErrorText>>,
+) {
+    if error_message.is_changed() {
+        *shown_for = 0.0;
+
+        for mut text in texts.iter_mut() {
+            text.sections[0].value = error_message.0.clone().unwrap_or_default();
+        }
+    } else if error_message.0.is_some() {
+        *shown_for += time.delta_seconds();
+
+        if *shown_for > ERROR_SECONDS {
+            error_message.0 = None;
+        }
+    }
+}
@@ -0,0 +1,246 @@
+//! A flattened representation of a [`Model`] for stepping large networks.
+//!
+//! Inputs are stored in CSR form, node states are packed 64 to a word, and
+//! each node's gate is resolved once at compile time, so that a step performs
+//! no allocation and touches no petgraph structures. Stepping is observably
+//! identical to [`Model::step`] under the And/Or/Nor semantics of
+//! [`NodeKind::update`].
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use petgraph::{graph::DiGraph, visit::IntoNodeReferences, EdgeDirection};
+use rayon::prelude::*;
+
+use super::{Model, NodeKind, NodeWeight};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Gate {
+    /// A gate whose output does not depend on its (absent) inputs.
+    Const(bool),
+    /// A pinned node, which keeps its current state (per lane, in a
+    /// `LaneModel`).
+    Hold,
+    And,
+    Or,
+    Nor,
+}
+
+/// Gates and CSR input lists of a model, shared by the compiled engines.
+#[derive(Clone, PartialEq, Eq)]
+pub(super) struct Topology {
+    pub(super) gates: Vec<Gate>,
+    offsets: Vec<u32>,
+    inputs: Vec<u32>,
+}
+
+impl Topology {
+    pub(super) fn new(graph: &DiGraph<NodeWeight, ()>) -> Self {
+        let node_count = graph.node_count();
+
+        let mut gates = Vec::with_capacity(node_count);
+        let mut offsets = Vec::with_capacity(node_count + 1);
+        let mut inputs = Vec::with_capacity(graph.edge_count());
+
+        offsets.push(0);
+
+        for (node, weight) in graph.node_references() {
+            let start = inputs.len();
+            inputs.extend(
+                graph
+                    .neighbors_directed(node, EdgeDirection::Incoming)
+                    .map(|adj| adj.index() as u32),
+            );
+
+            let has_inputs = inputs.len() > start;
+            gates.push(match weight.kind {
+                _ if weight.pinned => Gate::Hold,
+                NodeKind::And(_) if !has_inputs => Gate::Const(false),
+                NodeKind::Or(_) if !has_inputs => Gate::Const(false),
+                NodeKind::Nor(_) if !has_inputs => Gate::Const(true),
+                NodeKind::And(_) => Gate::And,
+                NodeKind::Or(_) => Gate::Or,
+                NodeKind::Nor(_) => Gate::Nor,
+            });
+
+            offsets.push(inputs.len() as u32);
+        }
+
+        Self {
+            gates,
+            offsets,
+            inputs,
+        }
+    }
+
+    pub(super) fn node_count(&self) -> usize {
+        self.gates.len()
+    }
+
+    pub(super) fn inputs(&self, node: usize) -> &[u32] {
+        &self.inputs[self.offsets[node] as usize..self.offsets[node + 1] as usize]
+    }
+}
+
+#[derive(Clone)]
+pub struct CompiledModel {
+    pub timestep: usize,
+    topology: Topology,
+    state: Vec<u64>,
+    next: Vec<u64>,
+}
+
+impl CompiledModel {
+    pub fn new(model: &Model) -> Self {
+        let state = model.packed_states();
+
+        Self {
+            timestep: model.timestep,
+            topology: Topology::new(&model.graph),
+            next: state.clone(),
+            state,
+        }
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.topology.node_count()
+    }
+
+    pub fn state(&self, node: usize) -> bool {
+        self.state[node / 64] >> (node % 64) & 1 == 1
+    }
+
+    /// Fraction of active nodes.
+    pub fn p_value(&self) -> f32 {
+        let active = self.state.iter().map(|word| word.count_ones()).sum::<u32>();
+        (self.node_count() as f32).recip() * active as f32
+    }
+
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.state.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn eval(&self, node: usize) -> bool {
+        let mut inputs = self
+            .topology
+            .inputs(node)
+            .iter()
+            .map(|&i| self.state(i as usize));
+
+        match self.topology.gates[node] {
+            Gate::Const(value) => value,
+            Gate::Hold => self.state(node),
+            Gate::And => inputs.all(|x| x),
+            Gate::Or => inputs.any(|x| x),
+            Gate::Nor => !inputs.any(|x| x),
+        }
+    }
+
+    fn eval_word(&self, word: usize) -> u64 {
+        let end = (word * 64 + 64).min(self.node_count());
+
+        (word * 64..end)
+            .map(|node| (self.eval(node) as u64) << (node % 64))
+            .fold(0, |acc, bit| acc | bit)
+    }
+
+    pub fn step(&mut self) {
+        let mut next = std::mem::take(&mut self.next);
+        next.iter_mut()
+            .enumerate()
+            .for_each(|(word, bits)| *bits = self.eval_word(word));
+
+        self.next = std::mem::replace(&mut self.state, next);
+        self.timestep += 1;
+    }
+
+    /// Same as [`step`](Self::step), evaluating blocks of 64 nodes in
+    /// parallel. Only worthwhile for networks of many thousands of nodes.
+    pub fn par_step(&mut self) {
+        let mut next = std::mem::take(&mut self.next);
+        next.par_iter_mut()
+            .enumerate()
+            .for_each(|(word, bits)| *bits = self.eval_word(word));
+
+        self.next = std::mem::replace(&mut self.state, next);
+        self.timestep += 1;
+    }
+
+    /// Writes the current node states and timestep back into `model`, which
+    /// must be the model this was compiled from.
+    pub fn write_to(&self, model: &mut Model) {
+        for (node, weight) in model.graph.node_weights_mut().enumerate() {
+            weight.kind = weight.kind.with_state(self.state(node));
+        }
+
+        model.timestep = self.timestep;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::model::generate::{self, GeneratorParams};
+
+    // Small random networks with random states and some nodes pinned.
+    fn cases() -> impl Iterator<Item = Model> {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        (0..200).map(move |_| {
+            let params = GeneratorParams {
+                node_count: rng.gen_range(1..300),
+                active_prob: rng.gen(),
+                ..Default::default()
+            };
+            let in_degree = rng.gen_range(0..=params.node_count.min(4));
+            let mut model = generate::kauffman(&params, in_degree, &mut rng);
+
+            for weight in model.graph.node_weights_mut() {
+                weight.pinned = rng.gen_bool(0.1);
+            }
+
+            model
+        })
+    }
+
+    fn states(model: &Model) -> Vec<bool> {
+        model
+            .graph
+            .raw_nodes()
+            .iter()
+            .map(|node| node.weight.kind.state())
+            .collect()
+    }
+
+    #[test]
+    fn step_matches_model() {
+        for mut model in cases() {
+            let mut compiled = CompiledModel::new(&model);
+            let mut parallel = compiled.clone();
+
+            for _ in 0..50 {
+                model.step();
+                compiled.step();
+                parallel.par_step();
+
+                let expected = states(&model);
+                for (node, &state) in expected.iter().enumerate() {
+                    assert_eq!(compiled.state(node), state);
+                    assert_eq!(parallel.state(node), state);
+                }
+                assert_eq!(compiled.p_value(), model.p_value());
+                assert_eq!(compiled.timestep, model.timestep);
+            }
+
+            let mut written = model.clone();
+            compiled.write_to(&mut written);
+            assert_eq!(states(&written), states(&model));
+        }
+    }
+}
@@ -1,5 +1,17 @@
+//! Cycle detection over the trajectory of a deterministic step function.
+//!
+//! [`brent`] and [`floyd`] take the initial state and the step function and
+//! use O(1) memory, re-running the trajectory from the start to find μ.
+//! [`CycleFinder`] is the incremental form for trajectories that are produced
+//! one state at a time elsewhere. Each returns the cycle as `μ..μ + λ`, the
+//! timesteps of its first traversal. None of them terminate if the trajectory
+//! never repeats.
+
 use std::ops::Range;
 
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct CycleFinder<T> {
     power: usize,
     lambda: usize,
@@ -7,7 +19,10 @@ pub struct CycleFinder<T> {
     tortoise: Option<T>,
 }
 
-impl<T> CycleFinder<T> where T: PartialEq {
+impl<T> CycleFinder<T>
+where
+    T: PartialEq,
+{
     pub fn new() -> Self {
         Self {
             power: 1,
@@ -23,6 +38,22 @@ impl<T> CycleFinder<T> where T: PartialEq {
         I: IntoIterator<Item = &'a T> + Copy,
         T: 'a,
     {
+        let lambda = self.next_lambda(next)?;
+
+        let mu = collection
+            .into_iter()
+            .zip(collection.into_iter().skip(lambda))
+            .take_while(|(tortoise, hare)| tortoise != hare)
+            .count();
+
+        self.mu = Some(mu);
+
+        Some(mu..(mu + lambda))
+    }
+
+    // The first phase of Brent's algorithm alone, which finds the cycle length
+    // without needing to look back at earlier elements.
+    pub fn next_lambda(&mut self, next: T) -> Option<usize> {
         if self.tortoise.is_none() {
             self.tortoise = Some(next);
             return None;
@@ -43,14 +74,174 @@ impl<T> CycleFinder<T> where T: PartialEq {
             return None;
         }
 
-        let mu = collection
-            .into_iter()
-            .zip(collection.into_iter().skip(self.lambda))
-            .take_while(|(tortoise, hare)| tortoise != hare)
-            .count();
+        Some(self.lambda)
+    }
+
+    /// Checks that this could be the state of a search fed `steps` elements
+    /// after the first, as a deserialized one might not be.
+    pub fn validate(&self, steps: usize) -> Result<(), String> {
+        if !self.power.is_power_of_two() || self.lambda >= self.power {
+            return Err(format!(
+                "invalid cycle search state (power {}, λ {})",
+                self.power, self.lambda
+            ));
+        }
+
+        if self.power / 2 > steps || self.lambda > steps {
+            return Err(format!("cycle search state ahead of its {} steps", steps));
+        }
+
+        Ok(())
+    }
+}
+
+fn nth<S, F>(start: &S, step: &F, n: usize) -> Option<S>
+where
+    F: Fn(&S) -> S,
+{
+    (0..n).fold(None, |state, _| Some(step(state.as_ref().unwrap_or(start))))
+}
+
+// Given λ, finds μ by walking two pointers λ apart from the start until they
+// meet. `None` stands for `start` itself, so `S` need not be `Clone`.
+fn find_mu<S, F>(start: &S, step: &F, lambda: usize) -> usize
+where
+    S: Eq,
+    F: Fn(&S) -> S,
+{
+    let mut tortoise = None;
+    let mut hare = nth(start, step, lambda).unwrap();
+    let mut mu = 0;
 
-        self.mu.insert(mu);
+    while *tortoise.as_ref().unwrap_or(start) != hare {
+        tortoise = Some(step(tortoise.as_ref().unwrap_or(start)));
+        hare = step(&hare);
+        mu += 1;
+    }
+
+    mu
+}
+
+/// Brent's algorithm: finds λ with fewer step evaluations than [`floyd`], at
+/// the cost of one clone per power of two.
+pub fn brent<S, F>(start: &S, step: F) -> Range<usize>
+where
+    S: Clone + Eq,
+    F: Fn(&S) -> S,
+{
+    let mut power = 1;
+    let mut lambda = 1;
+    let mut tortoise = start.clone();
+    let mut hare = step(start);
+
+    while tortoise != hare {
+        if power == lambda {
+            tortoise = hare.clone();
+            power *= 2;
+            lambda = 0;
+        }
+
+        hare = step(&hare);
+        lambda += 1;
+    }
 
-        Some(mu..(mu + self.lambda))
+    let mu = find_mu(start, &step, lambda);
+    mu..(mu + lambda)
+}
+
+/// Floyd's tortoise and hare.
+pub fn floyd<S, F>(start: &S, step: F) -> Range<usize>
+where
+    S: Eq,
+    F: Fn(&S) -> S,
+{
+    let mut tortoise = step(start);
+    let mut hare = step(&tortoise);
+
+    // Meet at some multiple of λ.
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&step(&hare));
+    }
+
+    let mut lambda = 1;
+    hare = step(&tortoise);
+
+    while tortoise != hare {
+        hare = step(&hare);
+        lambda += 1;
+    }
+
+    let mu = find_mu(start, &step, lambda);
+    mu..(mu + lambda)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    fn naive(start: u32, table: &[u32]) -> Range<usize> {
+        let mut seen = HashMap::new();
+        let mut state = start;
+
+        for timestep in 0.. {
+            if let Some(&mu) = seen.get(&state) {
+                return mu..timestep;
+            }
+
+            seen.insert(state, timestep);
+            state = table[state as usize];
+        }
+
+        unreachable!()
+    }
+
+    // Random functional graphs cover every shape of trajectory: fixed points,
+    // pure cycles, and long tails into short cycles.
+    fn cases() -> impl Iterator<Item = (u32, Vec<u32>)> {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        (0..2000).map(move |_| {
+            let size = rng.gen_range(1..200);
+            let table = (0..size).map(|_| rng.gen_range(0..size)).collect();
+            (rng.gen_range(0..size), table)
+        })
+    }
+
+    #[test]
+    fn brent_matches_naive() {
+        for (start, table) in cases() {
+            assert_eq!(brent(&start, |&s| table[s as usize]), naive(start, &table));
+        }
+    }
+
+    #[test]
+    fn floyd_matches_naive() {
+        for (start, table) in cases() {
+            assert_eq!(floyd(&start, |&s| table[s as usize]), naive(start, &table));
+        }
+    }
+
+    #[test]
+    fn cycle_finder_matches_naive() {
+        for (start, table) in cases() {
+            let mut cycle_finder = CycleFinder::new();
+            let mut trajectory = vec![start];
+
+            let cycle = loop {
+                let next = table[*trajectory.last().unwrap() as usize];
+                trajectory.push(next);
+
+                if let Some(cycle) = cycle_finder.check_next(&trajectory.as_slice(), next) {
+                    break cycle;
+                }
+            };
+
+            assert_eq!(cycle, naive(start, &table));
+        }
     }
 }
@@ -0,0 +1,264 @@
+//! Hand edits of a model's network, with undo and redo.
+
+use bevy::math::Vec3;
+use petgraph::graph::{DiGraph, EdgeIndex, NodeIndex};
+
+use super::{Model, NodeKind, NodeWeight};
+
+/// Number of edits that can be undone.
+const MAX_UNDO: usize = 256;
+
+#[derive(Debug, Clone)]
+pub enum Edit {
+    AddNode {
+        kind: NodeKind,
+        position: Vec3,
+    },
+    /// Removing a node moves the last node into its index.
+    RemoveNode(NodeIndex),
+    AddEdge(NodeIndex, NodeIndex),
+    RemoveEdge(EdgeIndex),
+    MoveNode(NodeIndex, Vec3),
+    /// Changes a node's gate, keeping its state.
+    SetGate(NodeIndex, NodeKind),
+}
+
+fn check_node(graph: &DiGraph<NodeWeight, ()>, node: NodeIndex) -> Result<NodeIndex, String> {
+    if node.index() < graph.node_count() {
+        Ok(node)
+    } else {
+        Err(format!("no node {}", node.index()))
+    }
+}
+
+fn node_weight(
+    graph: &mut DiGraph<NodeWeight, ()>,
+    node: NodeIndex,
+) -> Result<&mut NodeWeight, String> {
+    graph
+        .node_weight_mut(node)
+        .ok_or_else(|| format!("no node {}", node.index()))
+}
+
+impl Edit {
+    fn apply(&self, graph: &mut DiGraph<NodeWeight, ()>) -> Result<(), String> {
+        match *self {
+            Edit::AddNode { kind, position } => {
+                // Names may be taken by nodes that moved index after removals.
+                let name = (graph.node_count()..)
+                    .map(|index| format!("n{}", index))
+                    .find(|name| {
+                        graph
+                            .raw_nodes()
+                            .iter()
+                            .all(|node| node.weight.name != *name)
+                    })
+                    .unwrap();
+
+                graph.add_node(NodeWeight {
+                    name,
+                    kind,
+                    position,
+                    pinned: false,
+                });
+            }
+            Edit::RemoveNode(index) => {
+                graph.remove_node(check_node(graph, index)?);
+            }
+            Edit::AddEdge(source, target) => {
+                let (source, target) = (check_node(graph, source)?, check_node(graph, target)?);
+
+                if graph.contains_edge(source, target) {
+                    return Err(format!(
+                        "edge {} -> {} already exists",
+                        source.index(),
+                        target.index()
+                    ));
+                }
+
+                graph.add_edge(source, target, ());
+            }
+            Edit::RemoveEdge(index) => {
+                graph
+                    .remove_edge(index)
+                    .ok_or_else(|| format!("no edge {}", index.index()))?;
+            }
+            Edit::MoveNode(index, position) => {
+                node_weight(graph, index)?.position = position;
+            }
+            Edit::SetGate(index, kind) => {
+                let weight = node_weight(graph, index)?;
+                weight.kind = kind.with_state(weight.kind.state());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Networks from before the edits that can be undone, and after those that
+/// can be redone.
+#[derive(Debug, Clone, Default)]
+pub struct EditHistory {
+    undo: Vec<DiGraph<NodeWeight, ()>>,
+    redo: Vec<DiGraph<NodeWeight, ()>>,
+}
+
+impl EditHistory {
+    /// Applies `edit` to `model`, leaving it untouched on error.
+    pub fn apply(&mut self, model: &mut Model, edit: &Edit) -> Result<(), String> {
+        let before = model.graph.clone();
+        edit.apply(&mut model.graph)?;
+
+        if self.undo.len() == MAX_UNDO {
+            self.undo.remove(0);
+        }
+        self.undo.push(before);
+        self.redo.clear();
+
+        model.invalidate();
+
+        Ok(())
+    }
+
+    /// Returns whether there was an edit to undo.
+    pub fn undo(&mut self, model: &mut Model) -> bool {
+        Self::swap(&mut self.undo, &mut self.redo, model)
+    }
+
+    /// Returns whether there was an edit to redo.
+    pub fn redo(&mut self, model: &mut Model) -> bool {
+        Self::swap(&mut self.redo, &mut self.undo, model)
+    }
+
+    fn swap(
+        from: &mut Vec<DiGraph<NodeWeight, ()>>,
+        to: &mut Vec<DiGraph<NodeWeight, ()>>,
+        model: &mut Model,
+    ) -> bool {
+        if let Some(graph) = from.pop() {
+            to.push(std::mem::replace(&mut model.graph, graph));
+            model.invalidate();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::model::generate::{self, GeneratorParams};
+
+    type Topology = (Vec<(String, bool, Vec3)>, Vec<(usize, usize)>);
+
+    fn topology(model: &Model) -> Topology {
+        let nodes = model
+            .graph
+            .raw_nodes()
+            .iter()
+            .map(|node| {
+                let weight = &node.weight;
+                (weight.name.clone(), weight.kind.state(), weight.position)
+            })
+            .collect();
+        let mut edges = model
+            .graph
+            .raw_edges()
+            .iter()
+            .map(|edge| (edge.source().index(), edge.target().index()))
+            .collect::<Vec<_>>();
+        edges.sort_unstable();
+
+        (nodes, edges)
+    }
+
+    fn model(rng: &mut StdRng) -> Model {
+        let params = GeneratorParams {
+            node_count: rng.gen_range(2..20),
+            ..Default::default()
+        };
+        generate::kauffman(&params, 2, rng)
+    }
+
+    #[test]
+    fn undo_remove_node_restores_topology() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for _ in 0..100 {
+            let mut model = model(&mut rng);
+            let original = topology(&model);
+            let removed = NodeIndex::new(rng.gen_range(0..model.graph.node_count()));
+
+            let mut edits = EditHistory::default();
+            edits.apply(&mut model, &Edit::RemoveNode(removed)).unwrap();
+            let (nodes, _) = topology(&model);
+            assert_eq!(nodes.len(), original.0.len() - 1);
+
+            assert!(edits.undo(&mut model));
+            assert_eq!(topology(&model), original);
+
+            assert!(edits.redo(&mut model));
+            assert_eq!(topology(&model).0, nodes);
+        }
+    }
+
+    // Random edits, undos and redos, checked against the networks they should
+    // step between.
+    #[test]
+    fn undo_redo_matches_naive() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for _ in 0..100 {
+            let mut model = model(&mut rng);
+            let mut edits = EditHistory::default();
+            let (mut undone, mut redone) = (Vec::new(), Vec::new());
+
+            for _ in 0..50 {
+                let before = topology(&model);
+                let node_count = model.graph.node_count().max(1);
+                let node = |rng: &mut StdRng| NodeIndex::new(rng.gen_range(0..node_count + 1));
+
+                let edit = match rng.gen_range(0..8) {
+                    0 => Edit::AddNode {
+                        kind: NodeKind::And(rng.gen()),
+                        position: Vec3::new(rng.gen(), 0.0, rng.gen()),
+                    },
+                    1 => Edit::RemoveNode(node(&mut rng)),
+                    2 => Edit::AddEdge(node(&mut rng), node(&mut rng)),
+                    3 => Edit::RemoveEdge(EdgeIndex::new(
+                        rng.gen_range(0..model.graph.edge_count() + 1),
+                    )),
+                    4 => Edit::MoveNode(node(&mut rng), Vec3::new(rng.gen(), 0.0, rng.gen())),
+                    5 => Edit::SetGate(node(&mut rng), NodeKind::Nor(false)),
+                    6 => {
+                        assert_eq!(edits.undo(&mut model), !undone.is_empty());
+                        if let Some(expected) = undone.pop() {
+                            redone.push(before);
+                            assert_eq!(topology(&model), expected);
+                        }
+                        continue;
+                    }
+                    _ => {
+                        assert_eq!(edits.redo(&mut model), !redone.is_empty());
+                        if let Some(expected) = redone.pop() {
+                            undone.push(before);
+                            assert_eq!(topology(&model), expected);
+                        }
+                        continue;
+                    }
+                };
+
+                if edits.apply(&mut model, &edit).is_ok() {
+                    undone.push(before);
+                    redone.clear();
+                } else {
+                    assert_eq!(topology(&model), before, "{:?}", edit);
+                }
+            }
+        }
+    }
+}
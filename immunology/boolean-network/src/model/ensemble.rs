@@ -0,0 +1,215 @@
+use std::{collections::HashSet, io::Write};
+
+use rand::{rngs::StdRng, SeedableRng};
+use rayon::prelude::*;
+use serde::Serialize;
+
+use super::{
+    generate::{self, GeneratorParams},
+    lanes::{self, LANES},
+};
+
+const HISTOGRAM_BINS: usize = 20;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EnsembleConfig {
+    pub params: GeneratorParams,
+    /// Number of networks generated (M).
+    pub networks: usize,
+    /// Number of random initial states simulated per network (K).
+    pub initial_states: usize,
+    /// Runs that have not found a cycle after this many steps are abandoned.
+    pub max_steps: usize,
+    pub seed: u64,
+}
+
+/// The attractor reached by a single run.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Outcome {
+    pub mu: usize,
+    pub lambda: usize,
+    /// Mean fraction of active nodes over the attractor.
+    pub p: f32,
+    /// Smallest state hash on the cycle, identifying the attractor.
+    pub attractor: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Run {
+    pub network: usize,
+    pub run: usize,
+    pub outcome: Option<Outcome>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Histogram {
+    pub start: f64,
+    pub bin_width: f64,
+    pub counts: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Distribution {
+    pub count: usize,
+    pub mean: f64,
+    pub min: f64,
+    pub q25: f64,
+    pub median: f64,
+    pub q75: f64,
+    pub max: f64,
+    pub histogram: Histogram,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Summary {
+    pub config: EnsembleConfig,
+    pub unresolved: usize,
+    pub mu: Distribution,
+    pub lambda: Distribution,
+    pub attractors: Distribution,
+    pub p: Distribution,
+}
+
+pub struct Ensemble {
+    pub config: EnsembleConfig,
+    pub runs: Vec<Run>,
+}
+
+impl Ensemble {
+    pub fn run(config: &EnsembleConfig) -> Self {
+        let runs = (0..config.networks)
+            .into_par_iter()
+            .flat_map(|network| {
+                let mut rng = StdRng::seed_from_u64(config.seed.wrapping_add(network as u64));
+                let model = generate::build(&config.params, &mut rng);
+
+                let initial_models = (0..config.initial_states)
+                    .map(|_| {
+                        let mut initial = model.clone();
+                        generate::randomize_states(
+                            &mut initial,
+                            config.params.active_prob,
+                            &mut rng,
+                        );
+                        initial
+                    })
+                    .collect::<Vec<_>>();
+
+                initial_models
+                    .par_chunks(LANES)
+                    .enumerate()
+                    .flat_map_iter(|(chunk, initial)| {
+                        lanes::simulate_lanes(initial, config.max_steps)
+                            .into_iter()
+                            .enumerate()
+                            .map(move |(lane, outcome)| Run {
+                                network,
+                                run: chunk * LANES + lane,
+                                outcome,
+                            })
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        Self {
+            config: config.clone(),
+            runs,
+        }
+    }
+
+    pub fn summary(&self) -> Summary {
+        let outcomes = self
+            .runs
+            .iter()
+            .filter_map(|run| run.outcome)
+            .collect::<Vec<_>>();
+
+        let attractors = (0..self.config.networks)
+            .map(|network| {
+                self.runs
+                    .iter()
+                    .filter(|run| run.network == network)
+                    .filter_map(|run| run.outcome.map(|outcome| outcome.attractor))
+                    .collect::<HashSet<_>>()
+                    .len() as f64
+            })
+            .collect();
+
+        Summary {
+            config: self.config.clone(),
+            unresolved: self.runs.len() - outcomes.len(),
+            mu: Distribution::new(outcomes.iter().map(|o| o.mu as f64).collect()),
+            lambda: Distribution::new(outcomes.iter().map(|o| o.lambda as f64).collect()),
+            attractors: Distribution::new(attractors),
+            p: Distribution::new(outcomes.iter().map(|o| o.p as f64).collect()),
+        }
+    }
+
+    /// Writes one row per run; unresolved runs have empty outcome columns.
+    pub fn write_csv(&self, mut writer: impl Write) -> std::io::Result<()> {
+        writeln!(writer, "network,run,mu,lambda,p,attractor")?;
+
+        for run in &self.runs {
+            write!(writer, "{},{},", run.network, run.run)?;
+
+            match run.outcome {
+                Some(o) => writeln!(writer, "{},{},{},{}", o.mu, o.lambda, o.p, o.attractor)?,
+                None => writeln!(writer, ",,,")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Distribution {
+    pub fn new(mut samples: Vec<f64>) -> Self {
+        samples.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let count = samples.len();
+        let (min, max) = match (samples.first(), samples.last()) {
+            (Some(&min), Some(&max)) => (min, max),
+            _ => (f64::NAN, f64::NAN),
+        };
+
+        let bin_width = if max > min {
+            (max - min) / HISTOGRAM_BINS as f64
+        } else {
+            1.0
+        };
+
+        let mut counts = vec![0; if count > 0 { HISTOGRAM_BINS } else { 0 }];
+        for sample in &samples {
+            let bin = ((sample - min) / bin_width) as usize;
+            counts[bin.min(HISTOGRAM_BINS - 1)] += 1;
+        }
+
+        Self {
+            count,
+            mean: (count as f64).recip() * samples.iter().sum::<f64>(),
+            min,
+            q25: quantile(&samples, 0.25),
+            median: quantile(&samples, 0.5),
+            q75: quantile(&samples, 0.75),
+            max,
+            histogram: Histogram {
+                start: min,
+                bin_width,
+                counts,
+            },
+        }
+    }
+}
+
+// Linearly interpolated quantile of sorted samples.
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    if sorted.is_empty() {
+        return f64::NAN;
+    }
+
+    let pos = q * (sorted.len() - 1) as f64;
+    let (lower, upper) = (pos.floor() as usize, pos.ceil() as usize);
+
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (pos - lower as f64)
+}
@@ -0,0 +1,309 @@
+use std::str::FromStr;
+
+use bevy::math::Vec3;
+use petgraph::{graph::NodeIndex, visit::IntoNodeReferences};
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    Rng,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{Model, NodeKind, NodeWeight};
+
+/// How nodes are wired up.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Topology {
+    /// Nodes within connection distance of one another, see [`generate`].
+    #[default]
+    Geometric,
+    /// Inputs drawn from the whole network, see [`kauffman`].
+    Kauffman { in_degree: usize },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneratorParams {
+    pub node_count: usize,
+    /// Nodes are never placed closer than this to one another. The disk grows
+    /// with it, so that rejection sampling keeps the same packing density.
+    pub min_dist: f32,
+    /// Nodes within this distance of one another are connected.
+    pub max_connect_dist: f32,
+    /// Probability that a node starts out active.
+    pub active_prob: f64,
+    /// Relative weights of And, Or and Nor gates.
+    pub gate_mix: [f64; 3],
+    #[serde(default)]
+    pub topology: Topology,
+}
+
+impl Default for GeneratorParams {
+    fn default() -> Self {
+        Self {
+            node_count: 200,
+            min_dist: 3.0,
+            max_connect_dist: 5.0,
+            active_prob: 0.5,
+            gate_mix: [1.0, 1.0, 1.0],
+            topology: Topology::Geometric,
+        }
+    }
+}
+
+/// A single scalar field of [`GeneratorParams`], as scanned by a sweep.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub enum Param {
+    NodeCount,
+    MinDist,
+    MaxConnectDist,
+    ActiveProb,
+    AndWeight,
+    OrWeight,
+    NorWeight,
+}
+
+impl GeneratorParams {
+    /// Checks that the generators can build a network from these parameters.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.node_count == 0 {
+            return Err("invalid node count 0".to_string());
+        }
+
+        if !(self.min_dist.is_finite() && self.min_dist > 0.0) {
+            return Err(format!("invalid minimum distance {}", self.min_dist));
+        }
+
+        if !(self.max_connect_dist.is_finite() && self.max_connect_dist >= 0.0) {
+            return Err(format!(
+                "invalid connection distance {}",
+                self.max_connect_dist
+            ));
+        }
+
+        if !(0.0..=1.0).contains(&self.active_prob) {
+            return Err(format!("invalid active probability {}", self.active_prob));
+        }
+
+        if !self.gate_mix.iter().all(|weight| weight.is_finite()) {
+            return Err(format!("invalid gate mixture {:?}", self.gate_mix));
+        }
+
+        WeightedIndex::new(self.gate_mix)
+            .map(|_| ())
+            .map_err(|err| format!("invalid gate mixture {:?}: {}", self.gate_mix, err))
+    }
+
+    pub fn get(&self, param: Param) -> f64 {
+        match param {
+            Param::NodeCount => self.node_count as f64,
+            Param::MinDist => self.min_dist as f64,
+            Param::MaxConnectDist => self.max_connect_dist as f64,
+            Param::ActiveProb => self.active_prob,
+            Param::AndWeight => self.gate_mix[0],
+            Param::OrWeight => self.gate_mix[1],
+            Param::NorWeight => self.gate_mix[2],
+        }
+    }
+
+    pub fn set(&mut self, param: Param, value: f64) {
+        match param {
+            Param::NodeCount => self.node_count = value.round() as usize,
+            Param::MinDist => self.min_dist = value as f32,
+            Param::MaxConnectDist => self.max_connect_dist = value as f32,
+            Param::ActiveProb => self.active_prob = value,
+            Param::AndWeight => self.gate_mix[0] = value,
+            Param::OrWeight => self.gate_mix[1] = value,
+            Param::NorWeight => self.gate_mix[2] = value,
+        }
+    }
+}
+
+impl FromStr for Param {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "nodes" => Ok(Param::NodeCount),
+            "min-dist" => Ok(Param::MinDist),
+            "connect-dist" => Ok(Param::MaxConnectDist),
+            "active-prob" => Ok(Param::ActiveProb),
+            "and" => Ok(Param::AndWeight),
+            "or" => Ok(Param::OrWeight),
+            "nor" => Ok(Param::NorWeight),
+            _ => Err(format!("unknown generator parameter {:?}", name)),
+        }
+    }
+}
+
+/// Builds a network of the topology `params` ask for.
+pub fn build(params: &GeneratorParams, rng: &mut impl Rng) -> Model {
+    match params.topology {
+        Topology::Geometric => generate(params, rng),
+        Topology::Kauffman { in_degree } => kauffman(params, in_degree.min(params.node_count), rng),
+    }
+}
+
+/// Scatters nodes over a disk by rejection sampling and wires up every pair
+/// within connection distance, in a random direction.
+pub fn generate(params: &GeneratorParams, rng: &mut impl Rng) -> Model {
+    let gen_radius = (params.node_count as f32).sqrt() * params.min_dist * (2.0 / 3.0);
+    let gates = WeightedIndex::new(params.gate_mix).expect("invalid gate mixture");
+
+    let mut model = Model::new();
+
+    'outer: while model.graph.node_count() < params.node_count {
+        let radius = rng.gen_range(0.0..gen_radius);
+        let theta = rng.gen_range(0.0..std::f32::consts::TAU);
+
+        let pos = Vec3::new(theta.cos() * radius, 0.0, theta.sin() * radius);
+
+        let mut edges = Vec::new();
+        for (node, weight) in model.graph.node_references() {
+            let dist = weight.position.distance(pos);
+
+            if dist < params.min_dist {
+                continue 'outer;
+            }
+
+            if dist > params.max_connect_dist {
+                continue;
+            }
+
+            edges.push(node);
+        }
+
+        let node = model.graph.add_node(NodeWeight {
+            name: format!("n{}", model.graph.node_count()),
+            kind: match gates.sample(rng) {
+                0 => NodeKind::And(rng.gen_bool(params.active_prob)),
+                1 => NodeKind::Or(rng.gen_bool(params.active_prob)),
+                2 => NodeKind::Nor(rng.gen_bool(params.active_prob)),
+                _ => unreachable!(),
+            },
+            position: pos,
+            pinned: false,
+        });
+
+        for other in edges {
+            if rng.gen_bool(0.5) {
+                model.graph.add_edge(node, other, ());
+            } else {
+                model.graph.add_edge(other, node, ());
+            }
+        }
+    }
+
+    model
+}
+
+/// Builds a random NK network in which every node draws `in_degree` distinct
+/// inputs uniformly from the whole network. Unlike [`generate`] this scales to
+/// millions of nodes; positions are scattered over a disk for display only.
+pub fn kauffman(params: &GeneratorParams, in_degree: usize, rng: &mut impl Rng) -> Model {
+    let gen_radius = (params.node_count as f32).sqrt() * params.min_dist * (2.0 / 3.0);
+    let gates = WeightedIndex::new(params.gate_mix).expect("invalid gate mixture");
+
+    let mut model = Model::new();
+
+    for index in 0..params.node_count {
+        let radius = gen_radius * rng.gen::<f32>().sqrt();
+        let theta = rng.gen_range(0.0..std::f32::consts::TAU);
+
+        model.graph.add_node(NodeWeight {
+            name: format!("n{}", index),
+            kind: match gates.sample(rng) {
+                0 => NodeKind::And(rng.gen_bool(params.active_prob)),
+                1 => NodeKind::Or(rng.gen_bool(params.active_prob)),
+                2 => NodeKind::Nor(rng.gen_bool(params.active_prob)),
+                _ => unreachable!(),
+            },
+            position: Vec3::new(theta.cos() * radius, 0.0, theta.sin() * radius),
+            pinned: false,
+        });
+    }
+
+    for target in 0..params.node_count {
+        let sources = rand::seq::index::sample(rng, params.node_count, in_degree);
+
+        for source in sources.iter() {
+            model
+                .graph
+                .add_edge(NodeIndex::new(source), NodeIndex::new(target), ());
+        }
+    }
+
+    model
+}
+
+/// Assigns every node a fresh random state, keeping its gate.
+pub fn randomize_states(model: &mut Model, active_prob: f64, rng: &mut impl Rng) {
+    for weight in model.graph.node_weights_mut() {
+        weight.kind = weight.kind.with_state(rng.gen_bool(active_prob));
+    }
+
+    model.invalidate();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_rejects_degenerate_params() {
+        assert!(GeneratorParams::default().validate().is_ok());
+
+        let invalid = [
+            GeneratorParams {
+                node_count: 0,
+                ..Default::default()
+            },
+            GeneratorParams {
+                min_dist: 0.0,
+                ..Default::default()
+            },
+            GeneratorParams {
+                min_dist: f32::NAN,
+                ..Default::default()
+            },
+            GeneratorParams {
+                min_dist: f32::INFINITY,
+                ..Default::default()
+            },
+            GeneratorParams {
+                max_connect_dist: -1.0,
+                ..Default::default()
+            },
+            GeneratorParams {
+                max_connect_dist: f32::NAN,
+                ..Default::default()
+            },
+            GeneratorParams {
+                active_prob: -0.1,
+                ..Default::default()
+            },
+            GeneratorParams {
+                active_prob: f64::NAN,
+                ..Default::default()
+            },
+            GeneratorParams {
+                gate_mix: [1.0, -1.0, 1.0],
+                ..Default::default()
+            },
+            GeneratorParams {
+                gate_mix: [1.0, f64::NAN, 1.0],
+                ..Default::default()
+            },
+            GeneratorParams {
+                gate_mix: [1.0, f64::INFINITY, 1.0],
+                ..Default::default()
+            },
+            GeneratorParams {
+                gate_mix: [0.0; 3],
+                ..Default::default()
+            },
+        ];
+
+        for params in &invalid {
+            assert!(params.validate().is_err(), "{:?}", params);
+        }
+    }
+}
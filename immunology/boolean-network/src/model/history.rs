@@ -0,0 +1,516 @@
+use std::{collections::VecDeque, ops::Range};
+
+use serde::{Deserialize, Serialize};
+
+use super::{cycle::CycleFinder, Model};
+
+pub const DEFAULT_CAPACITY: usize = 4096;
+pub const DEFAULT_WINDOW: usize = 100;
+
+/// The most recent states of a model, one entry per timestep, kept in a ring
+/// buffer of fixed capacity. Node states themselves are only kept when enabled
+/// with [`with_states`](Self::with_states).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct History {
+    capacity: usize,
+    /// Number of latest entries covered by the rolling statistics.
+    pub window: usize,
+    start: usize,
+    state_hashes: VecDeque<u64>,
+    p_values: VecDeque<f32>,
+    /// Absent from the older entries of snapshots saved before it was kept.
+    #[serde(default)]
+    hamming_distances: VecDeque<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    states: Option<VecDeque<Vec<u64>>>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY, DEFAULT_WINDOW)
+    }
+}
+
+impl History {
+    pub fn new(capacity: usize, window: usize) -> Self {
+        let capacity = capacity.max(1);
+
+        Self {
+            capacity,
+            window,
+            start: 0,
+            state_hashes: VecDeque::with_capacity(capacity),
+            p_values: VecDeque::with_capacity(capacity),
+            hamming_distances: VecDeque::with_capacity(capacity),
+            states: None,
+        }
+    }
+
+    /// Also records node states, packed 64 to a word as by
+    /// [`Model::packed_states`]. Costs O(nodes) per step.
+    pub fn with_states(mut self) -> Self {
+        // Grown as needed, since a restored capacity may be implausibly large.
+        self.states = Some(VecDeque::new());
+        self
+    }
+
+    /// Checks the invariants a deserialized history may break, for a model of
+    /// `node_count` nodes.
+    pub fn validate(&self, node_count: usize) -> Result<(), String> {
+        let len = self.len();
+
+        if len > self.capacity || self.capacity == 0 {
+            return Err(format!(
+                "history of {} timesteps with capacity {}",
+                len, self.capacity
+            ));
+        }
+
+        if self.p_values.len() != len {
+            return Err(format!(
+                "history has {} P values for {} timesteps",
+                self.p_values.len(),
+                len
+            ));
+        }
+
+        if self.hamming_distances.len() > len {
+            return Err(format!(
+                "history has {} Hamming distances for {} timesteps",
+                self.hamming_distances.len(),
+                len
+            ));
+        }
+
+        if self.start.checked_add(len).is_none() {
+            return Err(format!("history start {} out of range", self.start));
+        }
+
+        if let Some(states) = &self.states {
+            if states.len() > len {
+                return Err(format!(
+                    "history has node states for {} of {} timesteps",
+                    states.len(),
+                    len
+                ));
+            }
+
+            let words = node_count.div_ceil(64);
+            if let Some(entry) = states.iter().find(|entry| entry.len() != words) {
+                return Err(format!(
+                    "history entry of {} words, expected {} for {} nodes",
+                    entry.len(),
+                    words,
+                    node_count
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Drops the recorded node states and stops recording them.
+    pub fn without_states(mut self) -> Self {
+        self.states = None;
+        self
+    }
+
+    /// Records `timestep`, which must follow the last one pushed. `states` is
+    /// only called when recording node states.
+    pub fn push(
+        &mut self,
+        timestep: usize,
+        state_hash: u64,
+        p_value: f32,
+        hamming_distance: u32,
+        states: impl FnOnce() -> Vec<u64>,
+    ) {
+        if self.is_empty() {
+            self.start = timestep;
+        }
+
+        if self.state_hashes.len() == self.capacity {
+            if self.hamming_distances.len() == self.capacity {
+                self.hamming_distances.pop_front();
+            }
+
+            self.state_hashes.pop_front();
+            self.p_values.pop_front();
+            self.start += 1;
+
+            // Recording may have started after the oldest entry.
+            if let Some(recorded) = &mut self.states {
+                if recorded.len() == self.capacity {
+                    recorded.pop_front();
+                }
+            }
+        }
+
+        self.state_hashes.push_back(state_hash);
+        self.p_values.push_back(p_value);
+        self.hamming_distances.push_back(hamming_distance);
+
+        if let Some(recorded) = &mut self.states {
+            recorded.push_back(states());
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.state_hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.state_hashes.is_empty()
+    }
+
+    /// Timesteps currently retained.
+    pub fn timesteps(&self) -> Range<usize> {
+        self.start..self.start + self.len()
+    }
+
+    /// State hashes, oldest first.
+    pub fn state_hashes(&self) -> &VecDeque<u64> {
+        &self.state_hashes
+    }
+
+    /// Fractions of active nodes, oldest first.
+    pub fn p_values(&self) -> &VecDeque<f32> {
+        &self.p_values
+    }
+
+    /// Numbers of nodes that changed state since the previous timestep, as
+    /// many of the latest entries as were recorded.
+    pub fn hamming_distances(&self) -> &VecDeque<u32> {
+        &self.hamming_distances
+    }
+
+    /// Drops the entries from `timestep` on.
+    pub fn truncate(&mut self, timestep: usize) {
+        let len = timestep.saturating_sub(self.start).min(self.len());
+
+        if let Some(states) = &mut self.states {
+            let unrecorded = self.state_hashes.len() - states.len();
+            states.truncate(len.saturating_sub(unrecorded));
+        }
+
+        let unrecorded = self.state_hashes.len() - self.hamming_distances.len();
+        self.hamming_distances
+            .truncate(len.saturating_sub(unrecorded));
+
+        self.state_hashes.truncate(len);
+        self.p_values.truncate(len);
+    }
+
+    /// Packed node states, oldest first, if recorded.
+    pub fn states(&self) -> Option<&VecDeque<Vec<u64>>> {
+        self.states.as_ref()
+    }
+
+    /// Packed node states at `timestep`, if recorded and still retained.
+    pub fn states_at(&self, timestep: usize) -> Option<&[u64]> {
+        let states = self.states.as_ref()?;
+        let index = timestep.checked_sub(self.start + self.len() - states.len())?;
+
+        states.get(index).map(Vec::as_slice)
+    }
+
+    /// Fraction of the recorded timesteps in which `node` was active, if node
+    /// states are recorded.
+    pub fn activity(&self, node: usize) -> Option<f32> {
+        let states = self.states.as_ref().filter(|states| !states.is_empty())?;
+        let active = states
+            .iter()
+            .filter(|states| states[node / 64] >> (node % 64) & 1 == 1)
+            .count();
+
+        Some(active as f32 / states.len() as f32)
+    }
+
+    fn windowed(&self) -> impl Iterator<Item = f32> + '_ {
+        let skip = self.p_values.len().saturating_sub(self.window);
+        self.p_values.iter().skip(skip).copied()
+    }
+
+    /// Mean of P over the window.
+    pub fn mean(&self) -> f32 {
+        let count = self.windowed().count();
+        (count as f32).recip() * self.windowed().sum::<f32>()
+    }
+
+    /// Population variance of P over the window.
+    pub fn variance(&self) -> f32 {
+        let (count, mean) = (self.windowed().count(), self.mean());
+        (count as f32).recip() * self.windowed().map(|p| (p - mean).powi(2)).sum::<f32>()
+    }
+
+    /// Autocorrelation of P at `lag` steps over the window, in `-1.0..=1.0`.
+    pub fn autocorrelation(&self, lag: usize) -> f32 {
+        let values = self.windowed().collect::<Vec<_>>();
+
+        if lag >= values.len() {
+            return f32::NAN;
+        }
+
+        let (mean, variance) = (self.mean(), self.variance());
+        let covariance = values
+            .iter()
+            .zip(&values[lag..])
+            .map(|(a, b)| (a - mean) * (b - mean))
+            .sum::<f32>();
+
+        covariance / (variance * values.len() as f32)
+    }
+}
+
+/// Detects the cycle of a model's trajectory from its state hashes as they
+/// are produced, without relying on the (bounded) history. Once Brent's
+/// algorithm has found λ, μ is recovered by re-running a copy of the initial
+/// model, so memory stays O(nodes) regardless of how long the search takes.
+#[derive(Clone)]
+pub struct StreamingCycleFinder {
+    pub(super) initial: Model,
+    pub(super) cycle_finder: CycleFinder<u64>,
+}
+
+impl StreamingCycleFinder {
+    /// `initial` must be the model in the state its trajectory starts from.
+    /// Cycles are reported in its timesteps.
+    pub fn new(initial: &Model) -> Self {
+        let mut initial = initial.clone();
+        initial.history = History::new(1, 1);
+
+        Self {
+            initial,
+            cycle_finder: CycleFinder::new(),
+        }
+    }
+
+    /// Timestep the search started from.
+    pub fn start(&self) -> usize {
+        self.initial.timestep
+    }
+
+    /// Feeds the hash returned by each [`Model::step`].
+    pub fn check_next(&mut self, state_hash: u64) -> Option<Range<usize>> {
+        let lambda = self.cycle_finder.next_lambda(state_hash)?;
+
+        let mut tortoise = self.initial.clone();
+        let mut hare = self.initial.clone();
+
+        for _ in 0..lambda {
+            hare.step();
+        }
+
+        let mut mu = 0;
+        while tortoise.state_hash() != hare.state_hash() {
+            tortoise.step();
+            hare.step();
+            mu += 1;
+        }
+
+        let mu = self.start() + mu;
+        Some(mu..(mu + lambda))
+    }
+
+    /// Number of timesteps of `cycle`, as found by
+    /// [`check_next`](Self::check_next), in which each node is active. Nodes
+    /// active in none or all of them are frozen on the attractor.
+    pub fn cycle_activity(&self, cycle: &Range<usize>) -> Vec<usize> {
+        let mut model = self.initial.clone();
+
+        for _ in self.start()..cycle.start {
+            model.step();
+        }
+
+        let mut counts = vec![0; model.graph.node_count()];
+
+        for _ in cycle.clone() {
+            for (count, node) in counts.iter_mut().zip(model.graph.raw_nodes()) {
+                if node.weight.kind.state() {
+                    *count += 1;
+                }
+            }
+
+            model.step();
+        }
+
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::model::{
+        cycle,
+        generate::{self, GeneratorParams},
+    };
+
+    struct Entry {
+        timestep: usize,
+        state_hash: u64,
+        p_value: f32,
+        states: Option<Vec<u64>>,
+    }
+
+    // Random pushes, truncations and starts of state recording, checked
+    // against a plain list of the entries that should be retained.
+    #[test]
+    fn ring_buffer_matches_naive() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for _ in 0..200 {
+            let capacity = rng.gen_range(1..20);
+            let mut history = History::new(capacity, capacity);
+            let mut naive = Vec::<Entry>::new();
+            let mut next: usize = rng.gen_range(0..100);
+
+            for _ in 0..100 {
+                match rng.gen_range(0..10) {
+                    0 => {
+                        history = history.with_states();
+                        for entry in &mut naive {
+                            entry.states = None;
+                        }
+                    }
+                    1 => {
+                        let timestep = rng.gen_range(next.saturating_sub(30)..=next);
+                        history.truncate(timestep);
+                        naive.retain(|entry| entry.timestep < timestep);
+                        next = naive.last().map_or(timestep, |entry| entry.timestep + 1);
+                    }
+                    _ => {
+                        let entry = Entry {
+                            timestep: next,
+                            state_hash: rng.gen(),
+                            p_value: rng.gen(),
+                            states: history.states().map(|_| vec![rng.gen()]),
+                        };
+                        let states = entry.states.clone().unwrap_or_default();
+                        history.push(entry.timestep, entry.state_hash, entry.p_value, 0, || {
+                            states
+                        });
+
+                        naive.push(entry);
+                        if naive.len() > capacity {
+                            naive.remove(0);
+                        }
+                        next += 1;
+                    }
+                }
+
+                let start = naive.first().map_or(0, |entry| entry.timestep);
+                if !naive.is_empty() {
+                    assert_eq!(history.timesteps(), start..next);
+                }
+                assert!(history
+                    .state_hashes()
+                    .iter()
+                    .eq(naive.iter().map(|e| &e.state_hash)));
+                assert!(history
+                    .p_values()
+                    .iter()
+                    .eq(naive.iter().map(|e| &e.p_value)));
+                assert_eq!(history.hamming_distances().len(), naive.len());
+
+                for timestep in start.saturating_sub(2)..next + 2 {
+                    let expected = naive
+                        .iter()
+                        .find(|entry| entry.timestep == timestep)
+                        .and_then(|entry| entry.states.as_deref());
+                    assert_eq!(history.states_at(timestep), expected);
+                }
+
+                history.validate(64).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn statistics_of_known_sequence() {
+        let mut history = History::new(8, 4).with_states();
+
+        // Node 0 is active in one of the windowed timesteps, node 65 in two.
+        for (timestep, &(p_value, states)) in [
+            (9.0, [0, 0]),
+            (9.0, [0, 0]),
+            (0.0, [1, 0]),
+            (1.0, [0, 2]),
+            (0.0, [0, 0]),
+            (1.0, [0, 2]),
+        ]
+        .iter()
+        .enumerate()
+        {
+            history.push(timestep, 0, p_value, 0, || states.to_vec());
+        }
+
+        assert_eq!(history.mean(), 0.5);
+        assert_eq!(history.variance(), 0.25);
+        assert_eq!(history.autocorrelation(0), 1.0);
+        assert_eq!(history.autocorrelation(1), -0.75);
+        assert_eq!(history.autocorrelation(2), 0.5);
+        assert!(history.autocorrelation(4).is_nan());
+
+        assert_eq!(history.activity(0), Some(1.0 / 6.0));
+        assert_eq!(history.activity(65), Some(2.0 / 6.0));
+        assert_eq!(History::new(8, 4).activity(0), None);
+    }
+
+    #[test]
+    fn streaming_matches_brent() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for _ in 0..50 {
+            let params = GeneratorParams {
+                node_count: rng.gen_range(1..30),
+                ..Default::default()
+            };
+            let in_degree = rng.gen_range(0..=params.node_count.min(3));
+            let mut model = generate::kauffman(&params, in_degree, &mut rng);
+            for _ in 0..rng.gen_range(0..5) {
+                model.step();
+            }
+
+            let step = |states: &Vec<u64>| {
+                let mut model = model.clone();
+                for (node, weight) in model.graph.node_weights_mut().enumerate() {
+                    weight.kind = weight
+                        .kind
+                        .with_state(states[node / 64] >> (node % 64) & 1 == 1);
+                }
+                model.invalidate();
+                model.step();
+                model.packed_states()
+            };
+            let expected = cycle::brent(&model.packed_states(), step);
+
+            let mut cycle_finder = StreamingCycleFinder::new(&model);
+            let mut running = model.clone();
+            let cycle = loop {
+                if let Some(cycle) = cycle_finder.check_next(running.step()) {
+                    break cycle;
+                }
+            };
+
+            let start = model.timestep;
+            assert_eq!(cycle, start + expected.start..start + expected.end);
+
+            // Activity over the cycle, counted along a fresh run.
+            let mut replay = model.clone();
+            let mut counts = vec![0; model.graph.node_count()];
+            while replay.timestep < cycle.end {
+                if replay.timestep >= cycle.start {
+                    for (count, node) in counts.iter_mut().zip(replay.graph.raw_nodes()) {
+                        *count += node.weight.kind.state() as usize;
+                    }
+                }
+                replay.step();
+            }
+
+            assert_eq!(cycle_finder.cycle_activity(&cycle), counts);
+        }
+    }
+}
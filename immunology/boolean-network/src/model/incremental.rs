@@ -0,0 +1,126 @@
+//! Event-driven stepping: a node can only change if one of its inputs changed
+//! on the previous step, so only the out-neighbours of flipped nodes are
+//! re-evaluated. The state hash and active node count are maintained
+//! incrementally as nodes flip.
+
+use petgraph::graph::{DiGraph, NodeIndex};
+
+use super::{
+    compiled::{Gate, Topology},
+    zobrist_key, NodeWeight,
+};
+
+#[derive(Clone)]
+pub(super) struct Incremental {
+    topology: Topology,
+    output_offsets: Vec<u32>,
+    outputs: Vec<u32>,
+    frontier: Vec<u32>,
+    queued: Vec<bool>,
+    flipped: Vec<u32>,
+    pub(super) state_hash: u64,
+    pub(super) active_count: usize,
+}
+
+impl Incremental {
+    pub(super) fn new(graph: &DiGraph<NodeWeight, ()>) -> Self {
+        let node_count = graph.node_count();
+
+        let mut output_offsets = vec![0; node_count + 1];
+        for edge in graph.raw_edges() {
+            output_offsets[edge.source().index() + 1] += 1;
+        }
+        for node in 0..node_count {
+            output_offsets[node + 1] += output_offsets[node];
+        }
+
+        let mut fill = output_offsets.clone();
+        let mut outputs = vec![0; graph.edge_count()];
+        for edge in graph.raw_edges() {
+            let source = edge.source().index();
+            outputs[fill[source] as usize] = edge.target().index() as u32;
+            fill[source] += 1;
+        }
+
+        let nodes = graph.raw_nodes();
+
+        Self {
+            topology: Topology::new(graph),
+            output_offsets,
+            outputs,
+            // Nothing is known about the first step, so every node is evaluated.
+            frontier: (0..node_count as u32).collect(),
+            queued: vec![true; node_count],
+            flipped: Vec::new(),
+            state_hash: (0..node_count)
+                .filter(|&node| nodes[node].weight.kind.state())
+                .fold(0, |hash, node| hash ^ zobrist_key(node as u64)),
+            active_count: nodes.iter().filter(|node| node.weight.kind.state()).count(),
+        }
+    }
+
+    /// Number of nodes that flipped on the last step.
+    pub(super) fn flipped_count(&self) -> usize {
+        self.flipped.len()
+    }
+
+    /// Advances `graph` by one synchronous step, which must hold the states
+    /// this was last built from or stepped to.
+    pub(super) fn step(&mut self, graph: &mut DiGraph<NodeWeight, ()>) {
+        let nodes = graph.raw_nodes();
+        let state = |node: u32| nodes[node as usize].weight.kind.state();
+
+        self.flipped.clear();
+
+        for &node in &self.frontier {
+            let mut inputs = self
+                .topology
+                .inputs(node as usize)
+                .iter()
+                .map(|&i| state(i));
+
+            let next = match self.topology.gates[node as usize] {
+                Gate::Const(value) => value,
+                Gate::Hold => state(node),
+                Gate::And => inputs.all(|x| x),
+                Gate::Or => inputs.any(|x| x),
+                Gate::Nor => !inputs.any(|x| x),
+            };
+
+            if next != state(node) {
+                self.flipped.push(node);
+            }
+
+            self.queued[node as usize] = false;
+        }
+
+        self.frontier.clear();
+
+        for &node in &self.flipped {
+            let weight = graph
+                .node_weight_mut(NodeIndex::new(node as usize))
+                .unwrap();
+            let next = !weight.kind.state();
+            weight.kind = weight.kind.with_state(next);
+
+            self.state_hash ^= zobrist_key(node as u64);
+            if next {
+                self.active_count += 1;
+            } else {
+                self.active_count -= 1;
+            }
+
+            let (start, end) = (
+                self.output_offsets[node as usize] as usize,
+                self.output_offsets[node as usize + 1] as usize,
+            );
+
+            for &target in &self.outputs[start..end] {
+                if !self.queued[target as usize] {
+                    self.queued[target as usize] = true;
+                    self.frontier.push(target);
+                }
+            }
+        }
+    }
+}
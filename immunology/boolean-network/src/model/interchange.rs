@@ -0,0 +1,670 @@
+//! Export of a model's graph to GraphML, GEXF and Graphviz DOT, and import
+//! of GraphML topologies and BoolNet (`.bnet`) rules.
+//!
+//! Every node carries its name, gate (`and`, `or` or `nor`), current state
+//! and position. Nodes lie in the model's x/z plane, which is written as the
+//! x/y plane that graph tools lay out in, with the model's height as z.
+
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    str::FromStr,
+};
+
+use bevy::math::Vec3;
+use petgraph::visit::IntoNodeReferences;
+
+use super::{Model, NodeKind, NodeWeight};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Format {
+    GraphMl,
+    Gexf,
+    Dot,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "graphml" => Ok(Format::GraphMl),
+            "gexf" => Ok(Format::Gexf),
+            "dot" => Ok(Format::Dot),
+            _ => Err(format!("unknown graph format {:?}", name)),
+        }
+    }
+}
+
+fn gate_name(kind: NodeKind) -> &'static str {
+    match kind {
+        NodeKind::And(_) => "and",
+        NodeKind::Or(_) => "or",
+        NodeKind::Nor(_) => "nor",
+    }
+}
+
+/// Parses a gate name as written by the exporters.
+pub fn parse_gate(name: &str, state: bool) -> Option<NodeKind> {
+    match name.to_ascii_lowercase().as_str() {
+        "and" => Some(NodeKind::And(state)),
+        "or" => Some(NodeKind::Or(state)),
+        "nor" => Some(NodeKind::Nor(state)),
+        _ => None,
+    }
+}
+
+/// Position of node `index` of `count` spread over a circle.
+fn on_circle(index: usize, count: usize) -> Vec3 {
+    let theta = std::f32::consts::TAU * index as f32 / count as f32;
+    let radius = count as f32;
+    Vec3::new(theta.cos() * radius, 0.0, theta.sin() * radius)
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+pub fn write(format: Format, model: &Model, writer: impl Write) -> io::Result<()> {
+    match format {
+        Format::GraphMl => write_graphml(model, writer),
+        Format::Gexf => write_gexf(model, writer),
+        Format::Dot => write_dot(model, writer),
+    }
+}
+
+fn write_graphml(model: &Model, mut writer: impl Write) -> io::Result<()> {
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+    )?;
+
+    for (key, kind) in &[
+        ("name", "string"),
+        ("kind", "string"),
+        ("state", "boolean"),
+        ("pinned", "boolean"),
+        ("x", "double"),
+        ("y", "double"),
+        ("z", "double"),
+    ] {
+        writeln!(
+            writer,
+            r#"  <key id="{0}" for="node" attr.name="{0}" attr.type="{1}"/>"#,
+            key, kind
+        )?;
+    }
+
+    writeln!(writer, r#"  <graph id="model" edgedefault="directed">"#)?;
+
+    for (node, weight) in model.graph.node_references() {
+        writeln!(writer, r#"    <node id="n{}">"#, node.index())?;
+        writeln!(
+            writer,
+            r#"      <data key="name">{}</data>"#,
+            escape_xml(&weight.name)
+        )?;
+        writeln!(
+            writer,
+            r#"      <data key="kind">{}</data>"#,
+            gate_name(weight.kind)
+        )?;
+        writeln!(
+            writer,
+            r#"      <data key="state">{}</data>"#,
+            weight.kind.state()
+        )?;
+        writeln!(
+            writer,
+            r#"      <data key="pinned">{}</data>"#,
+            weight.pinned
+        )?;
+        writeln!(
+            writer,
+            r#"      <data key="x">{}</data>"#,
+            weight.position.x
+        )?;
+        writeln!(
+            writer,
+            r#"      <data key="y">{}</data>"#,
+            weight.position.z
+        )?;
+        writeln!(
+            writer,
+            r#"      <data key="z">{}</data>"#,
+            weight.position.y
+        )?;
+        writeln!(writer, "    </node>")?;
+    }
+
+    for edge in model.graph.raw_edges() {
+        writeln!(
+            writer,
+            r#"    <edge source="n{}" target="n{}"/>"#,
+            edge.source().index(),
+            edge.target().index()
+        )?;
+    }
+
+    writeln!(writer, "  </graph>")?;
+    writeln!(writer, "</graphml>")
+}
+
+fn write_gexf(model: &Model, mut writer: impl Write) -> io::Result<()> {
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<gexf xmlns="http://gexf.net/1.2" xmlns:viz="http://gexf.net/1.2/viz" version="1.2">"#
+    )?;
+    writeln!(writer, r#"  <graph defaultedgetype="directed">"#)?;
+    writeln!(writer, r#"    <attributes class="node">"#)?;
+    writeln!(
+        writer,
+        r#"      <attribute id="kind" title="kind" type="string"/>"#
+    )?;
+    writeln!(
+        writer,
+        r#"      <attribute id="state" title="state" type="boolean"/>"#
+    )?;
+    writeln!(writer, "    </attributes>")?;
+    writeln!(writer, "    <nodes>")?;
+
+    for (node, weight) in model.graph.node_references() {
+        writeln!(
+            writer,
+            r#"      <node id="n{}" label="{}">"#,
+            node.index(),
+            escape_xml(&weight.name)
+        )?;
+        writeln!(writer, "        <attvalues>")?;
+        writeln!(
+            writer,
+            r#"          <attvalue for="kind" value="{}"/>"#,
+            gate_name(weight.kind)
+        )?;
+        writeln!(
+            writer,
+            r#"          <attvalue for="state" value="{}"/>"#,
+            weight.kind.state()
+        )?;
+        writeln!(writer, "        </attvalues>")?;
+        writeln!(
+            writer,
+            r#"        <viz:position x="{}" y="{}" z="{}"/>"#,
+            weight.position.x, weight.position.z, weight.position.y
+        )?;
+        writeln!(writer, "      </node>")?;
+    }
+
+    writeln!(writer, "    </nodes>")?;
+    writeln!(writer, "    <edges>")?;
+
+    for (id, edge) in model.graph.raw_edges().iter().enumerate() {
+        writeln!(
+            writer,
+            r#"      <edge id="e{}" source="n{}" target="n{}"/>"#,
+            id,
+            edge.source().index(),
+            edge.target().index()
+        )?;
+    }
+
+    writeln!(writer, "    </edges>")?;
+    writeln!(writer, "  </graph>")?;
+    writeln!(writer, "</gexf>")
+}
+
+/// Positions are pinned (`pos="x,y!"`) for `neato -n`.
+fn write_dot(model: &Model, mut writer: impl Write) -> io::Result<()> {
+    writeln!(writer, "digraph model {{")?;
+
+    for (node, weight) in model.graph.node_references() {
+        writeln!(
+            writer,
+            r#"  n{} [label="{}", kind="{}", state={}, pos="{},{}!"];"#,
+            node.index(),
+            escape_dot(&weight.name),
+            gate_name(weight.kind),
+            weight.kind.state(),
+            weight.position.x,
+            weight.position.z
+        )?;
+    }
+
+    for edge in model.graph.raw_edges() {
+        writeln!(
+            writer,
+            "  n{} -> n{};",
+            edge.source().index(),
+            edge.target().index()
+        )?;
+    }
+
+    writeln!(writer, "}}")
+}
+
+/// Reads the first graph of a GraphML document. Node attributes named as in
+/// the exported files are used where present (`label` is also accepted for
+/// the name); nodes without a gate get `default_kind`, and nodes without a
+/// position are spread over a circle. Undirected edges become a pair of
+/// opposing edges.
+pub fn read_graphml(src: &str, default_kind: NodeKind) -> Result<Model, String> {
+    let document = roxmltree::Document::parse(src).map_err(|err| err.to_string())?;
+    let root = document.root_element();
+
+    if !root.has_tag_name("graphml") {
+        return Err("not a GraphML document".to_string());
+    }
+
+    // Maps key ids to attribute names.
+    let keys = root
+        .children()
+        .filter(|child| child.has_tag_name("key"))
+        .filter_map(|key| {
+            let id = key.attribute("id")?;
+            Some((id, key.attribute("attr.name").unwrap_or(id)))
+        })
+        .collect::<HashMap<_, _>>();
+
+    let graph = root
+        .children()
+        .find(|child| child.has_tag_name("graph"))
+        .ok_or("missing graph element")?;
+    let directed = graph.attribute("edgedefault") != Some("undirected");
+
+    let nodes = graph
+        .children()
+        .filter(|child| child.has_tag_name("node"))
+        .collect::<Vec<_>>();
+
+    let mut model = Model::new();
+    let mut ids = HashMap::new();
+
+    for (index, node) in nodes.iter().enumerate() {
+        let id = node.attribute("id").ok_or("node without id")?;
+
+        let data = node
+            .children()
+            .filter(|child| child.has_tag_name("data"))
+            .filter_map(|data| {
+                let key = data.attribute("key")?;
+                let name = keys.get(key).copied().unwrap_or(key);
+                Some((name, data.text().unwrap_or("").trim()))
+            })
+            .collect::<HashMap<_, _>>();
+
+        let number = |name: &str| -> Result<Option<f32>, String> {
+            data.get(name)
+                .map(|value| {
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid {} {:?} of node {}", name, value, id))
+                })
+                .transpose()
+        };
+
+        let flag = |name: &str| match data.get(name).copied() {
+            Some("true") | Some("1") => Ok(true),
+            Some("false") | Some("0") | None => Ok(false),
+            Some(value) => Err(format!("invalid {} {:?} of node {}", name, value, id)),
+        };
+
+        let state = flag("state")?;
+
+        let kind = match data.get("kind") {
+            Some(name) => parse_gate(name, state)
+                .ok_or_else(|| format!("invalid kind {:?} of node {}", name, id))?,
+            None => default_kind.with_state(state),
+        };
+
+        let position = match (number("x")?, number("y")?) {
+            (Some(x), Some(y)) => Vec3::new(x, number("z")?.unwrap_or(0.0), y),
+            _ => on_circle(index, nodes.len()),
+        };
+
+        let name = data
+            .get("name")
+            .or_else(|| data.get("label"))
+            .copied()
+            .unwrap_or(id);
+
+        let index = model.graph.add_node(NodeWeight {
+            name: name.to_string(),
+            kind,
+            position,
+            pinned: flag("pinned")?,
+        });
+
+        if ids.insert(id, index).is_some() {
+            return Err(format!("duplicate node id {}", id));
+        }
+    }
+
+    for edge in graph.children().filter(|child| child.has_tag_name("edge")) {
+        let endpoint = |name: &str| {
+            let id = edge
+                .attribute(name)
+                .ok_or_else(|| format!("edge without {}", name))?;
+            ids.get(id)
+                .copied()
+                .ok_or_else(|| format!("edge {} unknown node {}", name, id))
+        };
+
+        let (source, target) = (endpoint("source")?, endpoint("target")?);
+
+        model.graph.add_edge(source, target, ());
+
+        let edge_directed = match edge.attribute("directed") {
+            Some(value) => value == "true",
+            None => directed,
+        };
+
+        if !edge_directed && source != target {
+            model.graph.add_edge(target, source, ());
+        }
+    }
+
+    Ok(model)
+}
+
+/// Reads BoolNet rules, one `target, function` line per node after an
+/// optional `targets, factors` header. Only the functions the gates compute
+/// are accepted: a conjunction (`a & b`) or disjunction (`a | b`) of inputs,
+/// or the negation of a disjunction (`!(a | b)` or `!a & !b`). Nodes start
+/// inactive and are spread over a circle.
+pub fn read_bnet(src: &str) -> Result<Model, String> {
+    let rules = src
+        .lines()
+        .enumerate()
+        .map(|(line, text)| (line + 1, text.split('#').next().unwrap().trim()))
+        .filter(|(_, text)| !text.is_empty())
+        .filter(|(_, text)| {
+            !text
+                .replace(' ', "")
+                .eq_ignore_ascii_case("targets,factors")
+        })
+        .map(|(line, text)| {
+            let (target, function) = text
+                .split_once(',')
+                .ok_or_else(|| format!("line {}: expected \"target, function\"", line))?;
+            Ok((line, target.trim(), function.trim()))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let mut model = Model::new();
+    let mut ids = HashMap::new();
+
+    for (index, &(line, target, _)) in rules.iter().enumerate() {
+        let node = model.graph.add_node(NodeWeight {
+            name: target.to_string(),
+            kind: NodeKind::Or(false),
+            position: on_circle(index, rules.len()),
+            pinned: false,
+        });
+
+        if ids.insert(target, node).is_some() {
+            return Err(format!("line {}: duplicate target {}", line, target));
+        }
+    }
+
+    for &(line, target, function) in &rules {
+        let (kind, inputs) = parse_bnet_function(function)
+            .ok_or_else(|| format!("line {}: unsupported function {:?}", line, function))?;
+
+        let node = ids[target];
+        model.graph[node].kind = kind;
+
+        for input in inputs {
+            let source = ids
+                .get(input)
+                .ok_or_else(|| format!("line {}: unknown input {}", line, input))?;
+            model.graph.add_edge(*source, node, ());
+        }
+    }
+
+    Ok(model)
+}
+
+/// Gate and inputs computing a BoolNet function, if a gate does.
+fn parse_bnet_function(function: &str) -> Option<(NodeKind, Vec<&str>)> {
+    let function = strip_parens(function.trim());
+
+    // Node names separated by `separator`, if nothing else.
+    fn names(terms: &str, separator: char) -> Option<Vec<&str>> {
+        terms
+            .split(separator)
+            .map(|name| strip_parens(name.trim()))
+            .map(|name| {
+                let valid = !name.is_empty()
+                    && name
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+                if valid {
+                    Some(name)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    if let Some(negated) = function.strip_prefix('!') {
+        let negated = negated.trim();
+        if negated.starts_with('(') && strip_parens(negated) != negated {
+            return Some((NodeKind::Nor(false), names(strip_parens(negated), '|')?));
+        }
+    }
+
+    if function.contains('&') {
+        let terms = function.split('&').map(str::trim).collect::<Vec<_>>();
+
+        if terms.iter().all(|term| term.starts_with('!')) {
+            let inputs = terms
+                .iter()
+                .map(|term| names(&term[1..], '|').filter(|names| names.len() == 1))
+                .collect::<Option<Vec<_>>>()?;
+            return Some((NodeKind::Nor(false), inputs.concat()));
+        }
+
+        return Some((NodeKind::And(false), names(function, '&')?));
+    }
+
+    if let Some(negated) = function.strip_prefix('!') {
+        let input = names(negated, '|').filter(|names| names.len() == 1)?;
+        return Some((NodeKind::Nor(false), input));
+    }
+
+    Some((NodeKind::Or(false), names(function, '|')?))
+}
+
+/// Strips parentheses enclosing all of `text`.
+fn strip_parens(mut text: &str) -> &str {
+    while let Some(inner) = text
+        .strip_prefix('(')
+        .and_then(|text| text.strip_suffix(')'))
+    {
+        let mut depth = 0;
+        let balanced = inner.chars().all(|c| {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            depth >= 0
+        });
+
+        if !balanced || depth != 0 {
+            break;
+        }
+
+        text = inner.trim();
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::model::generate::{self, GeneratorParams};
+
+    fn edges(model: &Model) -> Vec<(usize, usize)> {
+        model
+            .graph
+            .raw_edges()
+            .iter()
+            .map(|edge| (edge.source().index(), edge.target().index()))
+            .collect()
+    }
+
+    fn kinds(model: &Model) -> Vec<(&'static str, bool)> {
+        model
+            .graph
+            .raw_nodes()
+            .iter()
+            .map(|node| (gate_name(node.weight.kind), node.weight.kind.state()))
+            .collect()
+    }
+
+    #[test]
+    fn graphml_round_trip() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for _ in 0..50 {
+            let params = GeneratorParams {
+                node_count: rng.gen_range(1..50),
+                ..Default::default()
+            };
+            let in_degree = rng.gen_range(0..=params.node_count.min(3));
+            let mut model = generate::kauffman(&params, in_degree, &mut rng);
+
+            for (index, weight) in model.graph.node_weights_mut().enumerate() {
+                weight.name = format!("<{}> & \"{}\" 'x'", index, weight.name);
+                weight.pinned = rng.gen();
+                weight.position.y = rng.gen_range(-10.0..10.0);
+            }
+
+            let mut graphml = Vec::new();
+            write(Format::GraphMl, &model, &mut graphml).unwrap();
+            let read =
+                read_graphml(std::str::from_utf8(&graphml).unwrap(), NodeKind::And(false)).unwrap();
+
+            assert_eq!(edges(&read), edges(&model));
+            assert_eq!(kinds(&read), kinds(&model));
+
+            for (read, node) in read.graph.raw_nodes().iter().zip(model.graph.raw_nodes()) {
+                assert_eq!(read.weight.name, node.weight.name);
+                assert_eq!(read.weight.pinned, node.weight.pinned);
+                assert_eq!(read.weight.position, node.weight.position);
+            }
+        }
+    }
+
+    #[test]
+    fn reads_foreign_graphml() {
+        let graphml = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+              <key id="d0" for="node" attr.name="label" attr.type="string"/>
+              <key id="d1" for="node" attr.name="kind" attr.type="string"/>
+              <key id="d2" for="node" attr.name="state" attr.type="boolean"/>
+              <graph id="g" edgedefault="undirected">
+                <node id="a"><data key="d0">IL-2</data><data key="d1">NOR</data></node>
+                <node id="b"><data key="d2">1</data></node>
+                <node id="c"/>
+                <edge source="a" target="b"/>
+                <edge source="b" target="c" directed="true"/>
+                <edge source="c" target="c"/>
+              </graph>
+            </graphml>"#;
+
+        let model = read_graphml(graphml, NodeKind::And(false)).unwrap();
+        let names = model
+            .graph
+            .raw_nodes()
+            .iter()
+            .map(|node| node.weight.name.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, ["IL-2", "b", "c"]);
+        assert_eq!(
+            kinds(&model),
+            [("nor", false), ("and", true), ("and", false)]
+        );
+        assert_eq!(edges(&model), [(0, 1), (1, 0), (1, 2), (2, 2)]);
+    }
+
+    #[test]
+    fn rejects_invalid_graphml() {
+        let graph = |body: &str| {
+            format!(
+                r#"<graphml><key id="kind" attr.name="kind"/><graph>{}</graph></graphml>"#,
+                body
+            )
+        };
+
+        for body in &[
+            r#"<node id="a"/><node id="a"/>"#,
+            r#"<node id="a"/><edge source="a" target="b"/>"#,
+            r#"<node id="a"><data key="kind">xor</data></node>"#,
+        ] {
+            assert!(read_graphml(&graph(body), NodeKind::Or(false)).is_err());
+        }
+    }
+
+    #[test]
+    fn parses_bnet_functions() {
+        let gate = |function| {
+            parse_bnet_function(function).map(|(kind, inputs)| (gate_name(kind), inputs))
+        };
+
+        assert_eq!(gate("a & b"), Some(("and", vec!["a", "b"])));
+        assert_eq!(
+            gate("(a | b | NF.kB)"),
+            Some(("or", vec!["a", "b", "NF.kB"]))
+        );
+        assert_eq!(gate("a"), Some(("or", vec!["a"])));
+        assert_eq!(gate("!(a | b)"), Some(("nor", vec!["a", "b"])));
+        assert_eq!(gate("!a & !b"), Some(("nor", vec!["a", "b"])));
+        assert_eq!(gate("!a"), Some(("nor", vec!["a"])));
+
+        for mixed in &[
+            "a & b | c",
+            "a | !b",
+            "!a & b",
+            "!(a & b)",
+            "!(a | b) & c",
+            "a &",
+        ] {
+            assert_eq!(gate(mixed), None, "{}", mixed);
+        }
+    }
+
+    #[test]
+    fn reads_bnet() {
+        let bnet = "targets, factors\n\
+                    a, !(b | c) # comment\n\
+                    b, a & c\n\
+                    c, a\n";
+        let model = read_bnet(bnet).unwrap();
+
+        assert_eq!(
+            kinds(&model),
+            [("nor", false), ("and", false), ("or", false)]
+        );
+        assert_eq!(edges(&model), [(1, 0), (2, 0), (0, 1), (2, 1), (0, 2)]);
+
+        assert!(read_bnet("a, b").is_err());
+        assert!(read_bnet("a, a\na, a").is_err());
+        assert!(read_bnet("a").is_err());
+    }
+}
@@ -0,0 +1,270 @@
+//! Bit-parallel simulation of up to 64 trajectories of the same network.
+//!
+//! Each node's state is a `u64` whose bit `l` belongs to trajectory (lane)
+//! `l`, so a single pass of `&`, `|` and `!` over the inputs advances every
+//! lane at once.
+
+use super::{
+    compiled::{Gate, Topology},
+    cycle::CycleFinder,
+    ensemble::Outcome,
+    zobrist_key, Model,
+};
+
+pub const LANES: usize = 64;
+
+pub struct LaneModel {
+    pub timestep: usize,
+    topology: Topology,
+    lane_count: usize,
+    state: Vec<u64>,
+    next: Vec<u64>,
+    keys: Vec<u64>,
+}
+
+impl LaneModel {
+    /// Loads the node states of each of `initial` (1 to [`LANES`] models
+    /// sharing one topology, pins included) into its own lane.
+    pub fn new(initial: &[Model]) -> Self {
+        assert!(
+            (1..=LANES).contains(&initial.len()),
+            "1 to {} lanes, not {}",
+            LANES,
+            initial.len()
+        );
+
+        let topology = Topology::new(&initial[0].graph);
+        assert!(
+            initial[1..]
+                .iter()
+                .all(|model| Topology::new(&model.graph) == topology),
+            "lanes of differing networks"
+        );
+        let mut state = vec![0; topology.node_count()];
+
+        for (lane, model) in initial.iter().enumerate() {
+            for (node, weight) in model.graph.raw_nodes().iter().enumerate() {
+                state[node] |= (weight.weight.kind.state() as u64) << lane;
+            }
+        }
+
+        Self {
+            timestep: 0,
+            keys: (0..topology.node_count() as u64).map(zobrist_key).collect(),
+            topology,
+            lane_count: initial.len(),
+            next: state.clone(),
+            state,
+        }
+    }
+
+    pub fn lane_count(&self) -> usize {
+        self.lane_count
+    }
+
+    pub fn state(&self, lane: usize, node: usize) -> bool {
+        self.state[node] >> lane & 1 == 1
+    }
+
+    pub fn step(&mut self) {
+        let (topology, state) = (&self.topology, &self.state);
+
+        for (node, bits) in self.next.iter_mut().enumerate() {
+            let inputs = topology.inputs(node).iter().map(|&i| state[i as usize]);
+
+            *bits = match topology.gates[node] {
+                Gate::Const(value) => 0u64.wrapping_sub(value as u64),
+                Gate::Hold => state[node],
+                Gate::And => inputs.fold(!0, |acc, x| acc & x),
+                Gate::Or => inputs.fold(0, |acc, x| acc | x),
+                Gate::Nor => !inputs.fold(0, |acc, x| acc | x),
+            };
+        }
+
+        std::mem::swap(&mut self.state, &mut self.next);
+        self.timestep += 1;
+    }
+
+    /// Per-lane state hashes (matching [`Model::step`]) and active node counts of the current states.
+    pub fn lane_summaries(&self) -> ([u64; LANES], [u32; LANES]) {
+        let mut hashes = [0; LANES];
+        let mut counts = [0; LANES];
+
+        for (&bits, &key) in self.state.iter().zip(&self.keys) {
+            let mut remaining = bits;
+
+            while remaining != 0 {
+                let lane = remaining.trailing_zeros() as usize;
+                hashes[lane] ^= key;
+                counts[lane] += 1;
+                remaining &= remaining - 1;
+            }
+        }
+
+        (hashes, counts)
+    }
+}
+
+/// Steps up to 64 initial states of one network together until every lane's
+/// trajectory closes into a cycle, running a [`CycleFinder`] per lane.
+pub fn simulate_lanes(initial: &[Model], max_steps: usize) -> Vec<Option<Outcome>> {
+    struct Lane {
+        cycle_finder: CycleFinder<u64>,
+        state_hashes: Vec<u64>,
+        p_values: Vec<f32>,
+        outcome: Option<Outcome>,
+    }
+
+    let mut model = LaneModel::new(initial);
+    let p_scale = (model.topology.node_count() as f32).recip();
+
+    let (hashes, counts) = model.lane_summaries();
+    let mut lanes = (0..model.lane_count())
+        .map(|lane| Lane {
+            cycle_finder: CycleFinder::new(),
+            state_hashes: vec![hashes[lane]],
+            p_values: vec![p_scale * counts[lane] as f32],
+            outcome: None,
+        })
+        .collect::<Vec<_>>();
+
+    while model.timestep < max_steps && lanes.iter().any(|lane| lane.outcome.is_none()) {
+        model.step();
+
+        let (hashes, counts) = model.lane_summaries();
+
+        for (index, lane) in lanes.iter_mut().enumerate() {
+            if lane.outcome.is_some() {
+                continue;
+            }
+
+            lane.state_hashes.push(hashes[index]);
+            lane.p_values.push(p_scale * counts[index] as f32);
+
+            let cycle = lane
+                .cycle_finder
+                .check_next(&lane.state_hashes.as_slice(), hashes[index]);
+
+            if let Some(cycle) = cycle {
+                let lambda = cycle.len();
+
+                lane.outcome = Some(Outcome {
+                    mu: cycle.start,
+                    lambda,
+                    p: (lambda as f32).recip() * lane.p_values[cycle.clone()].iter().sum::<f32>(),
+                    attractor: *lane.state_hashes[cycle].iter().min().unwrap(),
+                });
+            }
+        }
+    }
+
+    lanes.into_iter().map(|lane| lane.outcome).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::model::generate::{self, GeneratorParams};
+
+    // Random networks with some nodes pinned, each loaded into a random number
+    // of lanes with random states, pinned nodes included.
+    fn cases() -> impl Iterator<Item = Vec<Model>> {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        (0..100).map(move |_| {
+            let params = GeneratorParams {
+                node_count: rng.gen_range(1..100),
+                ..Default::default()
+            };
+            let in_degree = rng.gen_range(0..=params.node_count.min(4));
+            let mut model = generate::kauffman(&params, in_degree, &mut rng);
+
+            for weight in model.graph.node_weights_mut() {
+                weight.pinned = rng.gen_bool(0.2);
+            }
+
+            (0..rng.gen_range(1..=LANES))
+                .map(|_| {
+                    let mut lane = model.clone();
+                    for weight in lane.graph.node_weights_mut() {
+                        weight.kind = weight.kind.with_state(rng.gen());
+                    }
+                    lane
+                })
+                .collect()
+        })
+    }
+
+    #[test]
+    fn lanes_match_model() {
+        for mut initial in cases() {
+            let mut lanes = LaneModel::new(&initial);
+
+            for _ in 0..50 {
+                lanes.step();
+                let (hashes, counts) = lanes.lane_summaries();
+
+                for (lane, model) in initial.iter_mut().enumerate() {
+                    assert_eq!(hashes[lane], model.step());
+
+                    for (node, weight) in model.graph.raw_nodes().iter().enumerate() {
+                        assert_eq!(lanes.state(lane, node), weight.weight.kind.state());
+                    }
+
+                    let active = model.p_value() * model.graph.node_count() as f32;
+                    assert_eq!(counts[lane], active.round() as u32);
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "lanes")]
+    fn rejects_no_lanes() {
+        LaneModel::new(&[]);
+    }
+
+    #[test]
+    #[should_panic(expected = "lanes")]
+    fn rejects_too_many_lanes() {
+        LaneModel::new(&vec![Model::new(); LANES + 1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "lanes of differing networks")]
+    fn rejects_differing_lanes() {
+        let model = cases().next().unwrap().remove(0);
+        let mut other = model.clone();
+        other.graph.add_edge(0.into(), 0.into(), ());
+
+        LaneModel::new(&[model, other]);
+    }
+
+    #[test]
+    fn simulate_lanes_matches_model() {
+        for initial in cases() {
+            let outcomes = simulate_lanes(&initial, 1000);
+
+            for (model, outcome) in initial.iter().zip(outcomes) {
+                let mut model = model.clone();
+                let mut seen = HashMap::new();
+                let mut state_hash = model.state_hash();
+
+                let (mu, timestep) = loop {
+                    if let Some(&mu) = seen.get(&state_hash) {
+                        break (mu, model.timestep);
+                    }
+                    seen.insert(state_hash, model.timestep);
+                    state_hash = model.step();
+                };
+
+                let outcome = outcome.unwrap();
+                assert_eq!((outcome.mu, outcome.lambda), (mu, timestep - mu));
+            }
+        }
+    }
+}
@@ -0,0 +1,589 @@
+//! Layouts computing node positions from the topology alone, for networks
+//! imported without coordinates.
+//!
+//! The force-directed layout (Fruchterman–Reingold) runs iteratively, so that
+//! the viewer can animate it; the layered layout (Sugiyama) is computed at
+//! once. Both treat edges as undirected when spacing nodes.
+//!
+//! Repulsion between all pairs of nodes is approximated by Barnes–Hut over an
+//! octree, so that an iteration takes O(n log n) rather than O(n²) time.
+
+use std::str::FromStr;
+
+use bevy::math::Vec3;
+use rand::Rng;
+
+use super::Model;
+
+/// Default distance between neighbouring nodes, between the generator's
+/// minimum and connection distances.
+pub const SPACING: f32 = 4.0;
+
+/// Iterations until the force-directed layout has cooled down.
+pub const ITERATIONS: usize = 300;
+
+/// Pull towards the origin, which keeps disconnected parts together.
+const GRAVITY: f32 = 0.2;
+
+/// Largest angle (cell size over distance) at which a cell of the octree
+/// repels as a single node.
+const THETA: f32 = 0.8;
+
+/// Most nodes kept in a cell of the octree before it is split.
+const LEAF_NODES: usize = 8;
+
+/// Depth at which cells are no longer split, bounding the octree when many
+/// nodes coincide.
+const MAX_DEPTH: usize = 24;
+
+/// Sweeps of the barycentre heuristic ordering the nodes of each layer.
+const ORDERING_SWEEPS: usize = 8;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Algorithm {
+    /// Force-directed in the x/z plane.
+    Force,
+    /// Force-directed in all three dimensions.
+    Force3d,
+    /// Layers along z following the edges, for signalling cascades.
+    Layered,
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "force" => Ok(Algorithm::Force),
+            "force-3d" => Ok(Algorithm::Force3d),
+            "layered" => Ok(Algorithm::Layered),
+            _ => Err(format!("unknown layout {:?}", name)),
+        }
+    }
+}
+
+pub struct Layout {
+    algorithm: Algorithm,
+    /// Distance between neighbouring nodes.
+    spacing: f32,
+    positions: Vec<Vec3>,
+    edges: Vec<(usize, usize)>,
+    temperature: f32,
+    cooling: f32,
+}
+
+impl Layout {
+    /// Starts from `model`'s positions, jittered so that coincident nodes
+    /// separate and a flat network can lift off the plane in 3D.
+    pub fn new(algorithm: Algorithm, model: &Model, spacing: f32, rng: &mut impl Rng) -> Self {
+        let edges = model
+            .graph
+            .raw_edges()
+            .iter()
+            .map(|edge| (edge.source().index(), edge.target().index()))
+            .filter(|(source, target)| source != target)
+            .collect::<Vec<_>>();
+
+        let mut positions = model
+            .graph
+            .raw_nodes()
+            .iter()
+            .map(|node| {
+                let mut jitter = || rng.gen_range(-0.5..0.5) * spacing;
+                let mut position = node.weight.position;
+                position.x += jitter();
+                position.z += jitter();
+
+                match algorithm {
+                    Algorithm::Force3d => position.y += jitter() * 4.0,
+                    _ => position.y = 0.0,
+                }
+
+                position
+            })
+            .collect::<Vec<_>>();
+
+        if let Algorithm::Layered = algorithm {
+            positions = layered(positions.len(), &edges, spacing);
+        }
+
+        let temperature = match algorithm {
+            Algorithm::Layered => 0.0,
+            _ => spacing * (positions.len() as f32).sqrt() * 0.25,
+        };
+
+        Self {
+            algorithm,
+            spacing,
+            positions,
+            edges,
+            temperature,
+            cooling: temperature / ITERATIONS as f32,
+        }
+    }
+
+    pub fn positions(&self) -> &[Vec3] {
+        &self.positions
+    }
+
+    /// Runs an iteration of the force-directed layout, returning `false` once
+    /// it has cooled down.
+    pub fn step(&mut self) -> bool {
+        if self.temperature <= 0.0 {
+            return false;
+        }
+
+        let k = self.spacing;
+        let mut displacements = repulsion(&self.positions, k, THETA);
+        let positions = &mut self.positions;
+
+        for &(source, target) in &self.edges {
+            let delta = positions[source] - positions[target];
+            let distance = delta.length().max(0.01);
+            let force = delta / distance * (distance * distance / k);
+
+            displacements[source] -= force;
+            displacements[target] += force;
+        }
+
+        for (position, mut displacement) in positions.iter_mut().zip(displacements) {
+            displacement -= *position * GRAVITY * position.length() / k;
+
+            if let Algorithm::Force = self.algorithm {
+                displacement.y = 0.0;
+            }
+
+            let length = displacement.length();
+            if length > 0.0 {
+                *position += displacement / length * length.min(self.temperature);
+            }
+        }
+
+        self.temperature -= self.cooling;
+        true
+    }
+
+    /// Runs the layout to completion.
+    pub fn finish(mut self) -> Vec<Vec3> {
+        while self.step() {}
+        self.positions
+    }
+}
+
+/// A cell of the octree approximating repulsion: its nodes' count and centre
+/// of mass, and either its nodes or its child cells.
+struct Cell {
+    mass: f32,
+    centre: Vec3,
+    /// Corner and edge length of the cube the cell covers.
+    min: Vec3,
+    size: f32,
+    contents: Contents,
+}
+
+enum Contents {
+    Nodes(Vec<usize>),
+    Children(Vec<usize>),
+}
+
+/// Builds the cell covering `nodes` in the cube at `min` with edge `size`,
+/// after its children, returning its index in `cells`.
+fn build(
+    cells: &mut Vec<Cell>,
+    positions: &[Vec3],
+    nodes: Vec<usize>,
+    min: Vec3,
+    size: f32,
+    depth: usize,
+) -> usize {
+    let mass = nodes.len() as f32;
+    let centre = nodes
+        .iter()
+        .fold(Vec3::ZERO, |sum, &node| sum + positions[node])
+        / mass;
+
+    // Coincident nodes can't be told apart by subdividing.
+    let contents = if nodes.len() <= LEAF_NODES || depth == MAX_DEPTH {
+        Contents::Nodes(nodes)
+    } else {
+        let half = size / 2.0;
+        let mut octants = vec![Vec::new(); 8];
+
+        for node in nodes {
+            let offset = positions[node] - min;
+            let octant = (offset.x >= half) as usize
+                | ((offset.y >= half) as usize) << 1
+                | ((offset.z >= half) as usize) << 2;
+            octants[octant].push(node);
+        }
+
+        let children = octants
+            .into_iter()
+            .enumerate()
+            .filter(|(_, nodes)| !nodes.is_empty())
+            .map(|(octant, nodes)| {
+                let corner = Vec3::new(
+                    (octant & 1) as f32,
+                    (octant >> 1 & 1) as f32,
+                    (octant >> 2 & 1) as f32,
+                );
+                build(
+                    cells,
+                    positions,
+                    nodes,
+                    min + corner * half,
+                    half,
+                    depth + 1,
+                )
+            })
+            .collect();
+
+        Contents::Children(children)
+    };
+
+    cells.push(Cell {
+        mass,
+        centre,
+        min,
+        size,
+        contents,
+    });
+    cells.len() - 1
+}
+
+/// Repulsion on each node from all others, by Barnes–Hut: a cell not holding
+/// the node and seen at an angle below `theta` pushes as one node of its mass
+/// at its centre. A `theta` of 0 sums every pair exactly.
+fn repulsion(positions: &[Vec3], k: f32, theta: f32) -> Vec<Vec3> {
+    if positions.is_empty() {
+        return Vec::new();
+    }
+
+    let (min, max) = positions.iter().fold(
+        (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+        |(min, max), &position| (min.min(position), max.max(position)),
+    );
+    let size = (max - min).max_element().max(0.01) * (1.0 + f32::EPSILON * 4.0);
+
+    let mut cells = Vec::new();
+    let root = build(
+        &mut cells,
+        positions,
+        (0..positions.len()).collect(),
+        min,
+        size,
+        0,
+    );
+
+    let push = |delta: Vec3, mass: f32| {
+        let distance = delta.length().max(0.01);
+        delta / distance * (mass * k * k / distance)
+    };
+
+    positions
+        .iter()
+        .enumerate()
+        .map(|(node, &position)| {
+            let mut displacement = Vec3::ZERO;
+            let mut stack = vec![root];
+
+            while let Some(cell) = stack.pop() {
+                let cell = &cells[cell];
+                let delta = position - cell.centre;
+                let offset = position - cell.min;
+                let far = offset.min_element() < 0.0 || offset.max_element() >= cell.size;
+
+                match &cell.contents {
+                    _ if far && cell.size < theta * delta.length() => {
+                        displacement += push(delta, cell.mass)
+                    }
+                    Contents::Nodes(nodes) => {
+                        for &other in nodes.iter().filter(|&&other| other != node) {
+                            displacement += push(position - positions[other], 1.0);
+                        }
+                    }
+                    Contents::Children(children) => stack.extend(children),
+                }
+            }
+
+            displacement
+        })
+        .collect()
+}
+
+/// Writes `positions` into `model`'s nodes.
+pub fn apply(model: &mut Model, positions: &[Vec3]) {
+    for (weight, &position) in model.graph.node_weights_mut().zip(positions) {
+        weight.position = position;
+    }
+}
+
+/// Sugiyama layout: edges closing cycles are reversed, nodes are layered by
+/// the longest path reaching them, and each layer is ordered by the
+/// barycentres of its neighbours in the adjacent layers. Edges spanning
+/// several layers are not broken up by dummy nodes.
+fn layered(node_count: usize, edges: &[(usize, usize)], spacing: f32) -> Vec<Vec3> {
+    let mut successors = vec![Vec::new(); node_count];
+    for &(source, target) in edges {
+        successors[source].push(target);
+    }
+
+    // Depth-first search, dropping the back edges that close cycles; they
+    // count as reversed from here on.
+    let mut on_stack = vec![false; node_count];
+    let mut visited = vec![false; node_count];
+    let mut acyclic = vec![Vec::new(); node_count];
+
+    for root in 0..node_count {
+        if visited[root] {
+            continue;
+        }
+
+        visited[root] = true;
+        on_stack[root] = true;
+        let mut stack = vec![(root, 0)];
+
+        while let Some((node, next)) = stack.last_mut() {
+            let node = *node;
+
+            match successors[node].get(*next) {
+                Some(&successor) => {
+                    *next += 1;
+
+                    if on_stack[successor] {
+                        acyclic[successor].push(node);
+                    } else {
+                        acyclic[node].push(successor);
+
+                        if !visited[successor] {
+                            visited[successor] = true;
+                            on_stack[successor] = true;
+                            stack.push((successor, 0));
+                        }
+                    }
+                }
+                None => {
+                    on_stack[node] = false;
+                    stack.pop();
+                }
+            }
+        }
+    }
+
+    // Longest path layering, in topological order.
+    let mut in_degrees = vec![0; node_count];
+    for successor in acyclic.iter().flatten() {
+        in_degrees[*successor] += 1;
+    }
+
+    let mut layer_of = vec![0; node_count];
+    let mut ready = (0..node_count)
+        .filter(|&node| in_degrees[node] == 0)
+        .collect::<Vec<_>>();
+
+    while let Some(node) = ready.pop() {
+        for &successor in &acyclic[node] {
+            layer_of[successor] = layer_of[successor].max(layer_of[node] + 1);
+            in_degrees[successor] -= 1;
+
+            if in_degrees[successor] == 0 {
+                ready.push(successor);
+            }
+        }
+    }
+
+    let layer_count = layer_of.iter().max().map_or(0, |max| max + 1);
+    let mut layers = vec![Vec::new(); layer_count];
+    for node in 0..node_count {
+        layers[layer_of[node]].push(node);
+    }
+
+    let mut neighbors = vec![Vec::new(); node_count];
+    for (node, successors) in acyclic.iter().enumerate() {
+        for &successor in successors {
+            neighbors[node].push(successor);
+            neighbors[successor].push(node);
+        }
+    }
+
+    // Centred offset of each node within its layer.
+    let mut offsets = vec![0.0; node_count];
+    let place = |layer: &[usize], offsets: &mut [f32]| {
+        for (index, &node) in layer.iter().enumerate() {
+            offsets[node] = index as f32 - (layer.len() - 1) as f32 / 2.0;
+        }
+    };
+
+    for layer in &layers {
+        place(layer, &mut offsets);
+    }
+
+    for sweep in 0..ORDERING_SWEEPS {
+        let order = (1..layer_count).map(|layer| {
+            if sweep % 2 == 0 {
+                (layer, layer - 1)
+            } else {
+                (layer_count - 1 - layer, layer_count - layer)
+            }
+        });
+
+        for (layer, adjacent) in order.collect::<Vec<_>>() {
+            let barycentre = |node: usize| {
+                let (sum, count) = neighbors[node]
+                    .iter()
+                    .filter(|&&neighbor| layer_of[neighbor] == adjacent)
+                    .fold((0.0, 0), |(sum, count), &neighbor| {
+                        (sum + offsets[neighbor], count + 1)
+                    });
+
+                if count == 0 {
+                    offsets[node]
+                } else {
+                    sum / count as f32
+                }
+            };
+
+            let mut keyed = layers[layer]
+                .iter()
+                .map(|&node| (barycentre(node), node))
+                .collect::<Vec<_>>();
+            keyed.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+            layers[layer] = keyed.into_iter().map(|(_, node)| node).collect();
+            place(&layers[layer], &mut offsets);
+        }
+    }
+
+    let depth = (layer_count.max(1) - 1) as f32 / 2.0;
+    (0..node_count)
+        .map(|node| {
+            Vec3::new(
+                offsets[node] * spacing,
+                0.0,
+                (layer_of[node] as f32 - depth) * spacing,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::model::generate::{self, GeneratorParams};
+
+    fn cases() -> impl Iterator<Item = Model> {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        (0..50).map(move |_| {
+            let params = GeneratorParams {
+                node_count: rng.gen_range(1..80),
+                ..Default::default()
+            };
+            let in_degree = rng.gen_range(0..=params.node_count.min(3));
+            generate::kauffman(&params, in_degree, &mut rng)
+        })
+    }
+
+    fn naive_repulsion(positions: &[Vec3], k: f32) -> Vec<Vec3> {
+        positions
+            .iter()
+            .enumerate()
+            .map(|(a, &position)| {
+                (0..positions.len())
+                    .filter(|&b| b != a)
+                    .map(|b| {
+                        let delta = position - positions[b];
+                        let distance = delta.length().max(0.01);
+                        delta / distance * (k * k / distance)
+                    })
+                    .fold(Vec3::ZERO, |sum, force| sum + force)
+            })
+            .collect()
+    }
+
+    // Random clouds, with some nodes coincident as in an import without
+    // positions.
+    fn clouds() -> impl Iterator<Item = Vec<Vec3>> {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        (0..100).map(move |_| {
+            let extent = rng.gen_range(1.0..50.0);
+            let mut positions = (0..rng.gen_range(0..200))
+                .map(|_| {
+                    let mut coordinate = || rng.gen_range(-extent..extent);
+                    Vec3::new(coordinate(), coordinate(), coordinate())
+                })
+                .collect::<Vec<_>>();
+            positions.extend(vec![Vec3::ZERO; rng.gen_range(0..20)]);
+            positions
+        })
+    }
+
+    #[test]
+    fn exact_repulsion_matches_all_pairs() {
+        for positions in clouds() {
+            let expected = naive_repulsion(&positions, SPACING);
+
+            for (found, expected) in repulsion(&positions, SPACING, 0.0).iter().zip(expected) {
+                assert!(found.distance(expected) <= 1e-3 * expected.length().max(1.0));
+            }
+        }
+    }
+
+    // Far cells only ever approximate, so the error is bounded relative to
+    // the largest force.
+    #[test]
+    fn approximate_repulsion_is_close() {
+        for positions in clouds() {
+            let expected = naive_repulsion(&positions, SPACING);
+            let scale = expected
+                .iter()
+                .map(|force| force.length())
+                .fold(1.0, f32::max);
+
+            for (found, expected) in repulsion(&positions, SPACING, THETA).iter().zip(expected) {
+                assert!(found.distance(expected) <= 0.1 * scale);
+            }
+        }
+    }
+
+    #[test]
+    fn force_stays_in_plane() {
+        for model in cases() {
+            let mut rng = StdRng::seed_from_u64(0);
+            let positions = Layout::new(Algorithm::Force, &model, SPACING, &mut rng).finish();
+
+            assert!(positions.iter().all(|position| position.is_finite()));
+            assert!(positions.iter().all(|position| position.y == 0.0));
+        }
+    }
+
+    #[test]
+    fn layered_follows_edges() {
+        for mut model in cases() {
+            let mut rng = StdRng::seed_from_u64(0);
+            let positions = Layout::new(Algorithm::Layered, &model, SPACING, &mut rng).finish();
+
+            // Edges closing cycles are reversed, but still span layers.
+            for edge in model.graph.raw_edges() {
+                let (source, target) = (edge.source().index(), edge.target().index());
+                if source != target {
+                    assert_ne!(positions[source].z, positions[target].z);
+                }
+            }
+
+            model.graph.retain_edges(|graph, edge| {
+                let (source, target) = graph.edge_endpoints(edge).unwrap();
+                source < target
+            });
+            let positions = Layout::new(Algorithm::Layered, &model, SPACING, &mut rng).finish();
+
+            for edge in model.graph.raw_edges() {
+                let (source, target) = (edge.source().index(), edge.target().index());
+                assert!(positions[target].z > positions[source].z);
+            }
+        }
+    }
+}
@@ -1,14 +1,30 @@
+pub mod compiled;
 pub mod cycle;
-
-use std::{
-    collections::hash_map::DefaultHasher,
-    hash::{Hash, Hasher},
-};
+pub mod edit;
+pub mod ensemble;
+pub mod generate;
+pub mod history;
+mod incremental;
+pub mod interchange;
+pub mod lanes;
+pub mod layout;
+pub mod query;
+pub mod raster;
+pub mod search;
+pub mod snapshot;
+pub mod sweep;
+pub mod trajectory;
+
+use std::hash::{Hash, Hasher};
 
 use bevy::math::Vec3;
-use petgraph::{graph::DiGraph, visit::IntoNodeReferences, EdgeDirection};
+use petgraph::{graph::DiGraph, visit::IntoNodeReferences};
+use serde::{Deserialize, Serialize};
+
+use history::History;
+use incremental::Incremental;
 
-#[derive(Debug, Clone, Copy, Hash)]
+#[derive(Debug, Clone, Copy, Hash, Serialize, Deserialize)]
 pub enum NodeKind {
     And(bool),
     Or(bool),
@@ -24,6 +40,14 @@ impl NodeKind {
         }
     }
 
+    pub fn with_state(&self, state: bool) -> Self {
+        match self {
+            NodeKind::And(_) => NodeKind::And(state),
+            NodeKind::Or(_) => NodeKind::Or(state),
+            NodeKind::Nor(_) => NodeKind::Nor(state),
+        }
+    }
+
     pub fn update(&self, inputs: impl Iterator<Item = bool>) -> Self {
         let mut peekable_inputs = inputs.peekable();
 
@@ -39,8 +63,11 @@ impl NodeKind {
 
 #[derive(Debug, Clone)]
 pub struct NodeWeight {
+    pub name: String,
     pub kind: NodeKind,
     pub position: Vec3,
+    /// A pinned node holds its state regardless of its inputs.
+    pub pinned: bool,
 }
 
 impl Hash for NodeWeight {
@@ -52,64 +79,119 @@ impl Hash for NodeWeight {
 #[derive(Clone)]
 pub struct Model {
     pub timestep: usize,
-    pub state_hashes: Vec<u64>,
-    pub p_values: Vec<f32>,
+    pub history: History,
     pub graph: DiGraph<NodeWeight, ()>,
+    engine: Option<Incremental>,
 }
 
 impl Model {
     pub fn new() -> Self {
         Self {
             timestep: Default::default(),
-            state_hashes: Default::default(),
-            p_values: Default::default(),
+            history: Default::default(),
             graph: Default::default(),
+            engine: Default::default(),
         }
     }
 
-    fn push_state_hash(&mut self) -> u64 {
-        let mut hasher = DefaultHasher::new();
-        self.hash(&mut hasher);
+    /// Discards cached stepping state. Must be called after changing the
+    /// graph or node states directly.
+    pub fn invalidate(&mut self) {
+        self.engine = None;
+    }
 
-        self.state_hashes.push(hasher.finish());
-        return hasher.finish();
+    fn engine(&mut self) -> &mut Incremental {
+        let graph = &self.graph;
+        self.engine.get_or_insert_with(|| Incremental::new(graph))
     }
 
-    pub fn step(&mut self) -> u64 {
-        if self.timestep == 0 {
-            self.push_state_hash();
+    pub fn state_hash(&mut self) -> u64 {
+        self.engine().state_hash
+    }
+
+    /// Fraction of active nodes.
+    pub fn p_value(&mut self) -> f32 {
+        let node_count = self.graph.node_count();
+        (node_count as f32).recip() * self.engine().active_count as f32
+    }
+
+    /// Returns to `timestep`, whose node states must still be recorded in the
+    /// history, discarding the history after it.
+    pub fn rewind(&mut self, timestep: usize) -> Result<(), String> {
+        let states = self
+            .history
+            .states_at(timestep)
+            .ok_or_else(|| format!("timestep {} is not in the history", timestep))?
+            .to_vec();
+
+        for (node, weight) in self.graph.node_weights_mut().enumerate() {
+            weight.kind = weight
+                .kind
+                .with_state(states[node / 64] >> (node % 64) & 1 == 1);
         }
 
-        let new_weights = self
-            .graph
-            .node_references()
-            .map(|(node, weight)| {
-                let input_states = self
-                    .graph
-                    .neighbors_directed(node, EdgeDirection::Incoming)
-                    .map(|adj| self.graph.node_weight(adj).unwrap().kind.state());
-
-                NodeWeight {
-                    kind: weight.kind.update(input_states),
-                    ..weight.clone()
-                }
-            })
-            .collect::<Vec<_>>();
-
-        let states = new_weights.iter().map(|weight| weight.kind.state());
-        let p_value = (states.len() as f32).recip() * states.filter(|&x| x).count() as f32;
-        self.p_values.insert(0, p_value);
+        self.timestep = timestep;
+        self.history.truncate(timestep + 1);
+        self.invalidate();
 
-        self.graph
-            .node_weights_mut()
-            .zip(new_weights)
-            .for_each(|(weight, new_weight)| {
-                *weight = new_weight;
+        Ok(())
+    }
+
+    /// Re-records the current timestep after node states or pins were
+    /// changed directly.
+    pub fn amend(&mut self) {
+        self.invalidate();
+
+        if !self.history.is_empty() {
+            let states = self.packed_states();
+            let hamming_distance = self
+                .timestep
+                .checked_sub(1)
+                .and_then(|previous| self.history.states_at(previous))
+                .map_or(0, |previous| {
+                    previous
+                        .iter()
+                        .zip(&states)
+                        .map(|(a, b)| (a ^ b).count_ones())
+                        .sum()
+                });
+
+            self.history.truncate(self.timestep);
+            self.push_history(hamming_distance);
+        }
+    }
+
+    /// Node states packed 64 to a word, node `i` in bit `i % 64` of word `i / 64`.
+    pub fn packed_states(&self) -> Vec<u64> {
+        pack_states(&self.graph)
+    }
+
+    fn push_history(&mut self, hamming_distance: u32) -> u64 {
+        let (state_hash, p_value) = (self.state_hash(), self.p_value());
+
+        let graph = &self.graph;
+        self.history
+            .push(self.timestep, state_hash, p_value, hamming_distance, || {
+                pack_states(graph)
             });
 
+        state_hash
+    }
+
+    pub fn step(&mut self) -> u64 {
+        // The history starts with the state stepping started from.
+        if self.history.is_empty() {
+            self.push_history(0);
+        }
+
+        let graph = &mut self.graph;
+        let engine = self.engine.get_or_insert_with(|| Incremental::new(graph));
+        engine.step(graph);
+        let hamming_distance = engine.flipped_count() as u32;
+
         self.timestep += 1;
 
-        self.push_state_hash()
+        self.push_history(hamming_distance)
     }
 }
 
@@ -120,3 +202,24 @@ impl Hash for Model {
             .for_each(|(_, weight)| weight.hash(state));
     }
 }
+
+fn pack_states(graph: &DiGraph<NodeWeight, ()>) -> Vec<u64> {
+    let mut words = vec![0; graph.node_count().div_ceil(64)];
+
+    for (node, weight) in graph.node_references() {
+        if weight.kind.state() {
+            words[node.index() / 64] |= 1 << (node.index() % 64);
+        }
+    }
+
+    words
+}
+
+// SplitMix64 of the node index. A state hashes to the XOR of the keys of its
+// active nodes, which can be updated one flip at a time.
+fn zobrist_key(node: u64) -> u64 {
+    let mut z = node.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,828 @@
+//! Temporal-logic queries over the state transition graph of a [`Model`].
+//!
+//! The networks are synchronous and deterministic, so every state has exactly
+//! one successor. Consequently the `A` and `E` path quantifiers of CTL coincide,
+//! and CTL and LTL formulas are both evaluated over the same lasso-shaped
+//! trajectories (a transient followed by a cycle).
+//!
+//! ```text
+//! query   := ("forall" | "exists")? formula
+//! formula := or ("->" formula)?
+//! or      := and ("|" and)*
+//! and     := unary ("&" unary)*
+//! unary   := "!" unary
+//!          | ("X" | "F" | "G" | "EX" | "AX" | "EF" | "AF" | "EG" | "AG") unary
+//!          | ("E" | "A")? "[" formula "U" formula "]"
+//!          | "(" formula ")" | "true" | "false" | node ("=" ("0" | "1"))?
+//! node    := NAME | '"' any characters but '"' '"'
+//! ```
+//!
+//! For example, `IL2 -> AF FOXP3=1` asks whether every state with IL2 active
+//! eventually reaches a state with FOXP3 active. Names that aren't plain
+//! identifiers, or that spell an operator, are quoted, as in `F "IL-2"`.
+//!
+//! Evaluation is explicit-state: `check` enumerates the whole state space and
+//! `check_current` simulates a single trajectory. There is no symbolic (BDD)
+//! evaluation, so questions about every state are limited to networks of
+//! [`MAX_EXPLICIT_NODES`] nodes.
+
+use std::collections::HashMap;
+
+use petgraph::{graph::NodeIndex, visit::IntoNodeReferences, EdgeDirection};
+
+use super::{Model, NodeKind};
+
+/// Largest network whose full state space `check` will enumerate.
+pub const MAX_EXPLICIT_NODES: usize = 20;
+
+#[derive(Debug, Clone)]
+pub enum Formula {
+    True,
+    False,
+    Atom { node: usize, value: bool },
+    Not(Box<Formula>),
+    And(Box<Formula>, Box<Formula>),
+    Or(Box<Formula>, Box<Formula>),
+    Implies(Box<Formula>, Box<Formula>),
+    Next(Box<Formula>),
+    Finally(Box<Formula>),
+    Globally(Box<Formula>),
+    Until(Box<Formula>, Box<Formula>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quantifier {
+    ForAll,
+    Exists,
+}
+
+#[derive(Debug, Clone)]
+pub struct Query {
+    pub quantifier: Quantifier,
+    pub formula: Formula,
+}
+
+/// A trajectory whose states from `cycle_start` onwards repeat forever.
+#[derive(Debug, Clone)]
+pub struct Trace {
+    pub states: Vec<Vec<bool>>,
+    pub cycle_start: usize,
+}
+
+/// The answer to a query, with a witness (for `exists`) or counterexample (for
+/// `forall`) when one exists.
+#[derive(Debug, Clone)]
+pub struct Verdict {
+    pub holds: bool,
+    pub trace: Option<Trace>,
+}
+
+impl Query {
+    pub fn parse(model: &Model, source: &str) -> Result<Self, String> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            model,
+            tokens: &tokens,
+            pos: 0,
+        };
+
+        let quantifier = match parser.peek() {
+            Some(Token::Ident(word)) if word == "forall" => {
+                parser.pos += 1;
+                Quantifier::ForAll
+            }
+            Some(Token::Ident(word)) if word == "exists" => {
+                parser.pos += 1;
+                Quantifier::Exists
+            }
+            _ => Quantifier::ForAll,
+        };
+
+        let formula = parser.formula()?;
+
+        if let Some(token) = parser.peek() {
+            return Err(format!("unexpected token {:?}", token));
+        }
+
+        Ok(Self {
+            quantifier,
+            formula,
+        })
+    }
+
+    /// Evaluates the query over every state of the network by explicit
+    /// enumeration. Only feasible for networks of up to [`MAX_EXPLICIT_NODES`]
+    /// nodes.
+    pub fn check(&self, model: &Model) -> Result<Verdict, String> {
+        let space = StateSpace::new(model)?;
+        let sat = evaluate(&space, &self.formula);
+
+        let found = match self.quantifier {
+            Quantifier::ForAll => sat.iter().position(|&x| !x),
+            Quantifier::Exists => sat.iter().position(|&x| x),
+        };
+
+        Ok(Verdict {
+            holds: (self.quantifier == Quantifier::ForAll) == found.is_none(),
+            trace: found.map(|state| space.trace(state)),
+        })
+    }
+
+    /// Evaluates the query at the current state of `model` only, by simulating
+    /// its trajectory until a state repeats. Works for networks of any size.
+    pub fn check_current(&self, model: &Model) -> Verdict {
+        let lasso = Lasso::new(model);
+        let holds = evaluate(&lasso, &self.formula)[0];
+
+        Verdict {
+            holds,
+            trace: Some(Trace {
+                states: lasso.states,
+                cycle_start: lasso.cycle_start,
+            }),
+        }
+    }
+}
+
+/// A finite structure in which every state has exactly one successor.
+trait Kripke {
+    fn len(&self) -> usize;
+    fn succ(&self, state: usize) -> usize;
+    fn label(&self, state: usize, node: usize) -> bool;
+}
+
+/// Input adjacency and gates of a model, detached from the graph weights.
+struct Dynamics {
+    kinds: Vec<NodeKind>,
+    inputs: Vec<Vec<usize>>,
+}
+
+impl Dynamics {
+    fn new(model: &Model) -> Self {
+        let (kinds, inputs) = model
+            .graph
+            .node_references()
+            .map(|(node, weight)| {
+                // A pinned node follows only itself.
+                if weight.pinned {
+                    return (NodeKind::Or(false), vec![node.index()]);
+                }
+
+                let inputs = model
+                    .graph
+                    .neighbors_directed(node, EdgeDirection::Incoming)
+                    .map(NodeIndex::index)
+                    .collect();
+
+                (weight.kind, inputs)
+            })
+            .unzip();
+
+        Self { kinds, inputs }
+    }
+
+    fn next(&self, state: &[bool]) -> Vec<bool> {
+        self.kinds
+            .iter()
+            .zip(&self.inputs)
+            .map(|(kind, inputs)| kind.update(inputs.iter().map(|&i| state[i])).state())
+            .collect()
+    }
+}
+
+/// Every state of a small network, encoded as the bits of its index.
+struct StateSpace {
+    node_count: usize,
+    succ: Vec<u32>,
+}
+
+impl StateSpace {
+    fn new(model: &Model) -> Result<Self, String> {
+        let node_count = model.graph.node_count();
+
+        if node_count > MAX_EXPLICIT_NODES {
+            return Err(format!(
+                "explicit state space limited to {} nodes (model has {})",
+                MAX_EXPLICIT_NODES, node_count
+            ));
+        }
+
+        let dynamics = Dynamics::new(model);
+        let gates = dynamics
+            .kinds
+            .iter()
+            .zip(&dynamics.inputs)
+            .map(|(kind, inputs)| (*kind, inputs.iter().fold(0u32, |mask, &i| mask | 1 << i)))
+            .collect::<Vec<_>>();
+
+        let succ = (0..1u32 << node_count)
+            .map(|state| {
+                gates
+                    .iter()
+                    .enumerate()
+                    .fold(0, |next, (node, &(kind, mask))| {
+                        let active = match kind {
+                            NodeKind::And(_) => mask != 0 && state & mask == mask,
+                            NodeKind::Or(_) => state & mask != 0,
+                            NodeKind::Nor(_) => state & mask == 0,
+                        };
+
+                        next | (active as u32) << node
+                    })
+            })
+            .collect();
+
+        Ok(Self { node_count, succ })
+    }
+
+    fn decode(&self, state: usize) -> Vec<bool> {
+        (0..self.node_count)
+            .map(|node| self.label(state, node))
+            .collect()
+    }
+
+    fn trace(&self, start: usize) -> Trace {
+        let mut seen = HashMap::new();
+        let mut states = Vec::new();
+        let mut state = start;
+
+        while !seen.contains_key(&state) {
+            seen.insert(state, states.len());
+            states.push(self.decode(state));
+            state = self.succ(state);
+        }
+
+        Trace {
+            states,
+            cycle_start: seen[&state],
+        }
+    }
+}
+
+impl Kripke for StateSpace {
+    fn len(&self) -> usize {
+        self.succ.len()
+    }
+
+    fn succ(&self, state: usize) -> usize {
+        self.succ[state] as usize
+    }
+
+    fn label(&self, state: usize, node: usize) -> bool {
+        state >> node & 1 == 1
+    }
+}
+
+/// The trajectory of a single initial state.
+struct Lasso {
+    states: Vec<Vec<bool>>,
+    cycle_start: usize,
+}
+
+impl Lasso {
+    fn new(model: &Model) -> Self {
+        let dynamics = Dynamics::new(model);
+
+        let mut seen = HashMap::new();
+        let mut states = Vec::new();
+        let mut state = model
+            .graph
+            .node_references()
+            .map(|(_, weight)| weight.kind.state())
+            .collect::<Vec<_>>();
+
+        while !seen.contains_key(&state) {
+            let next = dynamics.next(&state);
+            seen.insert(state.clone(), states.len());
+            states.push(state);
+            state = next;
+        }
+
+        Self {
+            states,
+            cycle_start: seen[&state],
+        }
+    }
+}
+
+impl Kripke for Lasso {
+    fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    fn succ(&self, state: usize) -> usize {
+        if state + 1 < self.states.len() {
+            state + 1
+        } else {
+            self.cycle_start
+        }
+    }
+
+    fn label(&self, state: usize, node: usize) -> bool {
+        self.states[state][node]
+    }
+}
+
+fn evaluate<K: Kripke>(k: &K, formula: &Formula) -> Vec<bool> {
+    let pointwise = |a: &Formula, b: &Formula, op: fn(bool, bool) -> bool| {
+        evaluate(k, a)
+            .into_iter()
+            .zip(evaluate(k, b))
+            .map(|(a, b)| op(a, b))
+            .collect()
+    };
+
+    match formula {
+        Formula::True => vec![true; k.len()],
+        Formula::False => vec![false; k.len()],
+        Formula::Atom { node, value } => (0..k.len())
+            .map(|state| k.label(state, *node) == *value)
+            .collect(),
+        Formula::Not(inner) => evaluate(k, inner).into_iter().map(|x| !x).collect(),
+        Formula::And(a, b) => pointwise(a, b, |a, b| a && b),
+        Formula::Or(a, b) => pointwise(a, b, |a, b| a || b),
+        Formula::Implies(a, b) => pointwise(a, b, |a, b| !a || b),
+        Formula::Next(inner) => {
+            let sat = evaluate(k, inner);
+            (0..k.len()).map(|state| sat[k.succ(state)]).collect()
+        }
+        Formula::Finally(inner) => {
+            let sat = evaluate(k, inner);
+            propagate(k, |state| if sat[state] { Some(true) } else { None }, false)
+        }
+        Formula::Globally(inner) => {
+            let sat = evaluate(k, inner);
+            propagate(k, |state| if sat[state] { None } else { Some(false) }, true)
+        }
+        Formula::Until(hold, goal) => {
+            let (hold, goal) = (evaluate(k, hold), evaluate(k, goal));
+            propagate(
+                k,
+                |state| match (hold[state], goal[state]) {
+                    (_, true) => Some(true),
+                    (false, false) => Some(false),
+                    (true, false) => None,
+                },
+                false,
+            )
+        }
+    }
+}
+
+// Solves `value(s) = decide(s).unwrap_or(value(succ(s)))` for every state.
+// States on a cycle where `decide` never answers take `on_cycle`, which selects
+// the least (false) or greatest (true) fixpoint.
+fn propagate<K: Kripke>(
+    k: &K,
+    decide: impl Fn(usize) -> Option<bool>,
+    on_cycle: bool,
+) -> Vec<bool> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Status {
+        Unknown,
+        Visiting,
+        Done(bool),
+    }
+
+    let mut status = vec![Status::Unknown; k.len()];
+    let mut path = Vec::new();
+
+    for start in 0..k.len() {
+        let mut state = start;
+
+        let value = loop {
+            match status[state] {
+                Status::Done(value) => break value,
+                Status::Visiting => break on_cycle,
+                Status::Unknown => {}
+            }
+
+            if let Some(value) = decide(state) {
+                break value;
+            }
+
+            status[state] = Status::Visiting;
+            path.push(state);
+            state = k.succ(state);
+        };
+
+        status[state] = Status::Done(value);
+        for state in path.drain(..) {
+            status[state] = Status::Done(value);
+        }
+    }
+
+    status
+        .into_iter()
+        .map(|status| status == Status::Done(true))
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    /// A node name in double quotes.
+    Quoted(String),
+    Number(String),
+    Symbol(&'static str),
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    const SYMBOLS: [&str; 8] = ["->", "!", "&", "|", "(", ")", "[", "]"];
+
+    let mut tokens = Vec::new();
+    let mut rest = source.trim_start();
+
+    while let Some(c) = rest.chars().next() {
+        if c.is_alphabetic() || c == '_' || c.is_ascii_digit() {
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+
+            let word = rest[..end].to_string();
+            tokens.push(if c.is_ascii_digit() {
+                Token::Number(word)
+            } else {
+                Token::Ident(word)
+            });
+
+            rest = &rest[end..];
+        } else if c == '"' {
+            let end = rest[1..]
+                .find('"')
+                .ok_or_else(|| format!("unterminated name {}", rest))?;
+
+            tokens.push(Token::Quoted(rest[1..end + 1].to_string()));
+            rest = &rest[end + 2..];
+        } else if c == '=' {
+            tokens.push(Token::Symbol("="));
+            rest = &rest[1..];
+        } else if let Some(symbol) = SYMBOLS.iter().find(|symbol| rest.starts_with(*symbol)) {
+            tokens.push(Token::Symbol(symbol));
+            rest = &rest[symbol.len()..];
+        } else {
+            return Err(format!("unexpected character {:?}", c));
+        }
+
+        rest = rest.trim_start();
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    model: &'a Model,
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<&'a Token, String> {
+        let token = self.peek().ok_or("unexpected end of query")?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn eat(&mut self, symbol: &'static str) -> bool {
+        if self.peek() == Some(&Token::Symbol(symbol)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: &'static str) -> Result<(), String> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            Err(format!("expected '{}'", symbol))
+        }
+    }
+
+    fn formula(&mut self) -> Result<Formula, String> {
+        let lhs = self.or()?;
+
+        if self.eat("->") {
+            Ok(Formula::Implies(Box::new(lhs), Box::new(self.formula()?)))
+        } else {
+            Ok(lhs)
+        }
+    }
+
+    fn or(&mut self) -> Result<Formula, String> {
+        let mut lhs = self.and()?;
+
+        while self.eat("|") {
+            lhs = Formula::Or(Box::new(lhs), Box::new(self.and()?));
+        }
+
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Formula, String> {
+        let mut lhs = self.unary()?;
+
+        while self.eat("&") {
+            lhs = Formula::And(Box::new(lhs), Box::new(self.unary()?));
+        }
+
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Formula, String> {
+        if self.eat("!") {
+            return Ok(Formula::Not(Box::new(self.unary()?)));
+        }
+
+        if self.eat("(") {
+            let inner = self.formula()?;
+            self.expect(")")?;
+            return Ok(inner);
+        }
+
+        if self.eat("[") {
+            return self.until();
+        }
+
+        match self.next()? {
+            Token::Ident(word) => match word.as_str() {
+                "true" => Ok(Formula::True),
+                "false" => Ok(Formula::False),
+                "X" | "EX" | "AX" => Ok(Formula::Next(Box::new(self.unary()?))),
+                "F" | "EF" | "AF" => Ok(Formula::Finally(Box::new(self.unary()?))),
+                "G" | "EG" | "AG" => Ok(Formula::Globally(Box::new(self.unary()?))),
+                "E" | "A" => {
+                    self.expect("[")?;
+                    self.until()
+                }
+                name => self.atom(name),
+            },
+            Token::Quoted(name) => self.atom(name),
+            token => Err(format!("unexpected token {:?}", token)),
+        }
+    }
+
+    fn until(&mut self) -> Result<Formula, String> {
+        let hold = self.formula()?;
+
+        match self.next()? {
+            Token::Ident(word) if word == "U" => {}
+            _ => return Err("expected 'U'".to_string()),
+        }
+
+        let goal = self.formula()?;
+        self.expect("]")?;
+
+        Ok(Formula::Until(Box::new(hold), Box::new(goal)))
+    }
+
+    fn atom(&mut self, name: &str) -> Result<Formula, String> {
+        let node = self
+            .model
+            .graph
+            .node_references()
+            .find(|(_, weight)| weight.name == name)
+            .map(|(node, _)| node.index())
+            .ok_or_else(|| format!("unknown node {:?}", name))?;
+
+        let value = if self.eat("=") {
+            match self.next()? {
+                Token::Number(value) if value == "0" => false,
+                Token::Number(value) if value == "1" => true,
+                token => return Err(format!("expected 0 or 1, found {:?}", token)),
+            }
+        } else {
+            true
+        };
+
+        Ok(Formula::Atom { node, value })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::model::generate::{self, GeneratorParams};
+
+    // Steps a copy of `model` from `state` with `Model::step` until a state
+    // repeats.
+    fn naive_trace(model: &Model, state: &[bool]) -> Trace {
+        let mut model = model.clone();
+        for (weight, &state) in model.graph.node_weights_mut().zip(state) {
+            weight.kind = weight.kind.with_state(state);
+        }
+        model.invalidate();
+
+        let mut states: Vec<Vec<bool>> = Vec::new();
+        loop {
+            let state = model
+                .graph
+                .raw_nodes()
+                .iter()
+                .map(|node| node.weight.kind.state())
+                .collect::<Vec<_>>();
+
+            if let Some(cycle_start) = states.iter().position(|seen| *seen == state) {
+                return Trace {
+                    states,
+                    cycle_start,
+                };
+            }
+
+            states.push(state);
+            model.step();
+        }
+    }
+
+    // The positions of `trace` visited from `at` onwards, each once.
+    fn path(trace: &Trace, at: usize) -> Vec<usize> {
+        if at < trace.cycle_start {
+            (at..trace.states.len()).collect()
+        } else {
+            (at..trace.states.len())
+                .chain(trace.cycle_start..at)
+                .collect()
+        }
+    }
+
+    fn naive(trace: &Trace, formula: &Formula, at: usize) -> bool {
+        let next = if at + 1 < trace.states.len() {
+            at + 1
+        } else {
+            trace.cycle_start
+        };
+
+        match formula {
+            Formula::True => true,
+            Formula::False => false,
+            Formula::Atom { node, value } => trace.states[at][*node] == *value,
+            Formula::Not(inner) => !naive(trace, inner, at),
+            Formula::And(a, b) => naive(trace, a, at) && naive(trace, b, at),
+            Formula::Or(a, b) => naive(trace, a, at) || naive(trace, b, at),
+            Formula::Implies(a, b) => !naive(trace, a, at) || naive(trace, b, at),
+            Formula::Next(inner) => naive(trace, inner, next),
+            Formula::Finally(inner) => path(trace, at).into_iter().any(|i| naive(trace, inner, i)),
+            Formula::Globally(inner) => path(trace, at).into_iter().all(|i| naive(trace, inner, i)),
+            Formula::Until(hold, goal) => {
+                for i in path(trace, at) {
+                    if naive(trace, goal, i) {
+                        return true;
+                    }
+                    if !naive(trace, hold, i) {
+                        return false;
+                    }
+                }
+                false
+            }
+        }
+    }
+
+    fn random_formula(rng: &mut StdRng, node_count: usize, depth: usize) -> Formula {
+        let boxed = |rng: &mut StdRng| Box::new(random_formula(rng, node_count, depth - 1));
+
+        match if depth == 0 { 0 } else { rng.gen_range(0..9) } {
+            0 => Formula::Atom {
+                node: rng.gen_range(0..node_count),
+                value: rng.gen(),
+            },
+            1 => Formula::Not(boxed(rng)),
+            2 => Formula::And(boxed(rng), boxed(rng)),
+            3 => Formula::Or(boxed(rng), boxed(rng)),
+            4 => Formula::Implies(boxed(rng), boxed(rng)),
+            5 => Formula::Next(boxed(rng)),
+            6 => Formula::Finally(boxed(rng)),
+            7 => Formula::Globally(boxed(rng)),
+            _ => Formula::Until(boxed(rng), boxed(rng)),
+        }
+    }
+
+    // Small random networks, some of their nodes pinned.
+    fn cases() -> impl Iterator<Item = (Model, Formula)> {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        (0..300).map(move |_| {
+            let params = GeneratorParams {
+                node_count: rng.gen_range(1..8),
+                ..Default::default()
+            };
+            let in_degree = rng.gen_range(0..=params.node_count.min(3));
+            let mut model = generate::kauffman(&params, in_degree, &mut rng);
+
+            for weight in model.graph.node_weights_mut() {
+                weight.pinned = rng.gen_bool(0.2);
+            }
+
+            let formula = random_formula(&mut rng, params.node_count, 3);
+            (model, formula)
+        })
+    }
+
+    #[test]
+    fn check_matches_enumeration() {
+        for (model, formula) in cases() {
+            let node_count = model.graph.node_count();
+            let sat = (0..1usize << node_count)
+                .map(|state| {
+                    let state = (0..node_count)
+                        .map(|node| state >> node & 1 == 1)
+                        .collect::<Vec<_>>();
+                    naive(&naive_trace(&model, &state), &formula, 0)
+                })
+                .collect::<Vec<_>>();
+
+            for quantifier in [Quantifier::ForAll, Quantifier::Exists] {
+                let verdict = Query {
+                    quantifier,
+                    formula: formula.clone(),
+                }
+                .check(&model)
+                .unwrap();
+
+                let expected = match quantifier {
+                    Quantifier::ForAll => sat.iter().all(|&x| x),
+                    Quantifier::Exists => sat.iter().any(|&x| x),
+                };
+                assert_eq!(verdict.holds, expected, "{:?} {:?}", quantifier, formula);
+
+                // The trace starts at a witness or counterexample.
+                if let Some(trace) = verdict.trace {
+                    let naive_trace = naive_trace(&model, &trace.states[0]);
+                    assert_eq!(trace.states, naive_trace.states);
+                    assert_eq!(trace.cycle_start, naive_trace.cycle_start);
+                    assert_eq!(naive(&trace, &formula, 0), quantifier == Quantifier::Exists);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn check_current_matches_simulation() {
+        for (model, formula) in cases() {
+            let state = model
+                .graph
+                .raw_nodes()
+                .iter()
+                .map(|node| node.weight.kind.state())
+                .collect::<Vec<_>>();
+
+            let verdict = Query {
+                quantifier: Quantifier::ForAll,
+                formula: formula.clone(),
+            }
+            .check_current(&model);
+
+            assert_eq!(
+                verdict.holds,
+                naive(&naive_trace(&model, &state), &formula, 0),
+                "{:?}",
+                formula
+            );
+        }
+    }
+
+    #[test]
+    fn parses_operators_and_names() {
+        let mut model = generate::kauffman(
+            &GeneratorParams {
+                node_count: 3,
+                ..Default::default()
+            },
+            2,
+            &mut StdRng::seed_from_u64(0),
+        );
+
+        let query = Query::parse(&model, "exists n0 -> AF n1=0 & E[n0 U !n2]").unwrap();
+        assert_eq!(query.quantifier, Quantifier::Exists);
+        assert!(Query::parse(&model, "F n3").is_err());
+        assert!(Query::parse(&model, "n0 n1").is_err());
+
+        assert!(Query::parse(&model, "F \"n0").is_err());
+        assert!(Query::parse(&model, "F \"n0 \"").is_err());
+
+        // Quoted names may contain anything but quotes, and spell operators.
+        for name in ["IL-2", "2B4", "a.b c", "F", "forall", "U"] {
+            model.graph.node_weights_mut().next().unwrap().name = name.to_string();
+
+            let source = format!("G n1 -> F \"{}\"=0", name);
+            let query = Query::parse(&model, &source).unwrap();
+            assert_eq!(query.quantifier, Quantifier::ForAll, "{}", name);
+            match query.formula {
+                Formula::Implies(_, finally) => match *finally {
+                    Formula::Finally(atom) => match *atom {
+                        Formula::Atom { node, value } => assert_eq!((node, value), (0, false)),
+                        formula => panic!("{:?}", formula),
+                    },
+                    formula => panic!("{:?}", formula),
+                },
+                formula => panic!("{:?}", formula),
+            }
+        }
+    }
+}
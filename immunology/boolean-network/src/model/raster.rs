@@ -0,0 +1,283 @@
+//! Space-time raster plots of a model's recorded history: one row per node
+//! and one column per timestep, coloured by state. Rows may instead cover
+//! bands of consecutive nodes, coloured by the fraction active, to bound the
+//! height of the plot for large networks.
+//!
+//! Cells before μ are grey, cells of the first period `μ..μ + λ` gold and
+//! cells of later periods blue, so that μ and λ show as colour boundaries.
+//! SVG output also draws lines at both boundaries.
+
+use std::{
+    io::{self, Write},
+    ops::Range,
+    str::FromStr,
+};
+
+use super::Model;
+
+const TRANSIENT: [[u8; 4]; 2] = [[40, 40, 48, 255], [230, 230, 235, 255]];
+const FIRST_PERIOD: [[u8; 4]; 2] = [[70, 50, 10, 255], [255, 200, 40, 255]];
+const LATER_PERIODS: [[u8; 4]; 2] = [[20, 35, 70, 255], [90, 160, 255, 255]];
+
+/// Size of a cell in SVG output, in pixels.
+const SVG_CELL: usize = 4;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Format {
+    Png,
+    Svg,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "png" => Ok(Format::Png),
+            "svg" => Ok(Format::Svg),
+            _ => Err(format!("unknown raster format {:?}", name)),
+        }
+    }
+}
+
+pub struct Raster {
+    /// Timestep of the first column.
+    pub first: usize,
+    pub columns: usize,
+    pub rows: usize,
+    nodes: usize,
+    /// Nodes per row.
+    band: usize,
+    cycle: Option<Range<usize>>,
+    states: Vec<Vec<u64>>,
+}
+
+impl Raster {
+    /// Covers the last `max_columns` timesteps whose node states `model`'s
+    /// history recorded. `cycle` is the range found by the cycle finder.
+    pub fn new(
+        model: &Model,
+        cycle: &Option<Range<usize>>,
+        max_columns: usize,
+    ) -> Result<Self, String> {
+        let history = &model.history;
+        let recorded = history
+            .states()
+            .ok_or("the history does not record node states")?;
+
+        let columns = recorded.len().min(max_columns);
+        let states = recorded
+            .iter()
+            .skip(recorded.len() - columns)
+            .cloned()
+            .collect();
+
+        Ok(Self {
+            first: history.timesteps().end - columns,
+            columns,
+            rows: model.graph.node_count(),
+            nodes: model.graph.node_count(),
+            band: 1,
+            cycle: cycle.clone(),
+            states,
+        })
+    }
+
+    /// Merges consecutive nodes into bands so that there are at most
+    /// `max_rows` rows.
+    pub fn with_max_rows(mut self, max_rows: usize) -> Self {
+        self.band = self.nodes.div_ceil(max_rows.max(1)).max(1);
+        self.rows = self.nodes.div_ceil(self.band);
+        self
+    }
+
+    pub fn state(&self, node: usize, column: usize) -> bool {
+        self.states[column][node / 64] >> (node % 64) & 1 == 1
+    }
+
+    fn palette(&self, column: usize) -> &'static [[u8; 4]; 2] {
+        let timestep = self.first + column;
+
+        match &self.cycle {
+            Some(cycle) if timestep >= cycle.end => &LATER_PERIODS,
+            Some(cycle) if timestep >= cycle.start => &FIRST_PERIOD,
+            _ => &TRANSIENT,
+        }
+    }
+
+    fn color(&self, row: usize, column: usize) -> [u8; 4] {
+        let nodes = row * self.band..((row + 1) * self.band).min(self.nodes);
+        let active = nodes
+            .clone()
+            .filter(|&node| self.state(node, column))
+            .count();
+
+        let [inactive, full] = self.palette(column);
+        let fraction = active as f32 / nodes.len() as f32;
+        let mut color = [0; 4];
+
+        for (channel, (&from, &to)) in color.iter_mut().zip(inactive.iter().zip(full)) {
+            *channel = (from as f32 + (to as f32 - from as f32) * fraction).round() as u8;
+        }
+
+        color
+    }
+
+    /// RGBA pixels, one per cell, a row at a time.
+    pub fn pixels(&self) -> Vec<u8> {
+        (0..self.rows)
+            .flat_map(|row| (0..self.columns).flat_map(move |column| self.color(row, column)))
+            .collect()
+    }
+
+    pub fn write(&self, format: Format, writer: impl Write) -> Result<(), String> {
+        if self.rows == 0 || self.columns == 0 {
+            return Err("nothing to plot".to_string());
+        }
+
+        match format {
+            Format::Png => self.write_png(writer),
+            Format::Svg => self.write_svg(writer).map_err(|err| err.to_string()),
+        }
+    }
+
+    fn write_png(&self, writer: impl Write) -> Result<(), String> {
+        let mut encoder = png::Encoder::new(writer, self.columns as u32, self.rows as u32);
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&self.pixels()))
+            .map_err(|err| err.to_string())
+    }
+
+    fn write_svg(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(
+            writer,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}" shape-rendering="crispEdges">"#,
+            self.columns * SVG_CELL,
+            self.rows * SVG_CELL,
+            self.columns,
+            self.rows
+        )?;
+
+        // One rectangle per run of equally coloured cells.
+        for row in 0..self.rows {
+            let mut start = 0;
+
+            for column in 1..=self.columns {
+                let color = self.color(row, start);
+
+                if column < self.columns && self.color(row, column) == color {
+                    continue;
+                }
+
+                writeln!(
+                    writer,
+                    r##"  <rect x="{}" y="{}" width="{}" height="1" fill="#{:02x}{:02x}{:02x}"/>"##,
+                    start,
+                    row,
+                    column - start,
+                    color[0],
+                    color[1],
+                    color[2]
+                )?;
+
+                start = column;
+            }
+        }
+
+        if let Some(cycle) = &self.cycle {
+            for (label, timestep) in &[("μ", cycle.start), ("μ + λ", cycle.end)] {
+                if (self.first..=self.first + self.columns).contains(timestep) {
+                    writeln!(
+                        writer,
+                        r#"  <line x1="{0}" y1="0" x2="{0}" y2="{1}" stroke="red" stroke-width="0.25"><title>{2} = {3}</title></line>"#,
+                        timestep - self.first,
+                        self.rows,
+                        label,
+                        timestep
+                    )?;
+                }
+            }
+        }
+
+        writeln!(writer, "</svg>")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec3;
+
+    use super::*;
+    use crate::model::{history::History, NodeKind, NodeWeight};
+
+    // Three nodes over four timesteps, with the cycle 1..3.
+    fn known(max_columns: usize) -> Raster {
+        let mut model = Model::new();
+        for index in 0..3 {
+            model.graph.add_node(NodeWeight {
+                name: format!("n{}", index),
+                kind: NodeKind::Or(false),
+                position: Vec3::ZERO,
+                pinned: false,
+            });
+        }
+
+        model.history = History::new(8, 8).with_states();
+        for (timestep, &states) in [0b001, 0b010, 0b100, 0b011].iter().enumerate() {
+            model.history.push(timestep, 0, 0.0, 0, || vec![states]);
+        }
+
+        Raster::new(&model, &Some(1..3), max_columns).unwrap()
+    }
+
+    fn pixel(raster: &Raster, row: usize, column: usize) -> [u8; 4] {
+        let index = (row * raster.columns + column) * 4;
+        let pixels = raster.pixels();
+        [
+            pixels[index],
+            pixels[index + 1],
+            pixels[index + 2],
+            pixels[index + 3],
+        ]
+    }
+
+    #[test]
+    fn pixels_follow_states_and_cycle() {
+        let raster = known(16);
+        assert_eq!((raster.first, raster.columns, raster.rows), (0, 4, 3));
+        assert_eq!(raster.pixels().len(), 3 * 4 * 4);
+
+        let palettes = [TRANSIENT, FIRST_PERIOD, FIRST_PERIOD, LATER_PERIODS];
+        let states = [0b001, 0b010, 0b100, 0b011];
+
+        for row in 0..3 {
+            for column in 0..4 {
+                let state = states[column] >> row & 1;
+                assert_eq!(pixel(&raster, row, column), palettes[column][state]);
+            }
+        }
+
+        let latest = known(2);
+        assert_eq!((latest.first, latest.columns), (2, 2));
+        assert_eq!(pixel(&latest, 2, 0), FIRST_PERIOD[1]);
+    }
+
+    #[test]
+    fn bands_blend_their_nodes() {
+        let raster = known(16).with_max_rows(2);
+        assert_eq!(raster.rows, 2);
+        assert_eq!(raster.pixels().len(), 2 * 4 * 4);
+
+        // Nodes 0 and 1 are both active at timestep 3, one of them before.
+        assert_eq!(pixel(&raster, 0, 3), LATER_PERIODS[1]);
+        assert_eq!(pixel(&raster, 1, 3), LATER_PERIODS[0]);
+        assert_eq!(pixel(&raster, 0, 0), [135, 135, 142, 255]);
+
+        assert_eq!(known(16).with_max_rows(3).rows, 3);
+        assert_eq!(known(16).with_max_rows(1).rows, 1);
+    }
+}
@@ -0,0 +1,221 @@
+//! The search for a model's cycle, stepped in slices so that it can run in the
+//! background until the cycle is found or its step budget is spent.
+
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    cycle::CycleFinder,
+    generate::GeneratorParams,
+    history::{History, StreamingCycleFinder},
+    snapshot::{Snapshot, Trajectory},
+    Model,
+};
+
+/// Steps a search takes before giving up on finding the cycle.
+pub const DEFAULT_BUDGET: usize = 1_000_000;
+
+#[derive(Clone)]
+pub struct Search {
+    pub model: Model,
+    pub cycle_finder: StreamingCycleFinder,
+    pub cycle: Option<Range<usize>>,
+    /// Per-node activity over the cycle, once found.
+    pub cycle_activity: Option<Vec<usize>>,
+    /// Timestep at which the search gives up.
+    pub limit: usize,
+}
+
+/// A search to be run elsewhere, such as in the web build's worker.
+#[derive(Serialize, Deserialize)]
+pub struct Request {
+    snapshot: Snapshot,
+    limit: usize,
+}
+
+/// Progress of a search run elsewhere, without the network, which the
+/// requesting side already has.
+#[derive(Serialize, Deserialize)]
+pub struct Progress {
+    compute: Trajectory,
+    cycle_finder: CycleFinder<u64>,
+    cycle: Option<Range<usize>>,
+    cycle_activity: Option<Vec<usize>>,
+}
+
+impl Search {
+    /// Continues the search of `cycle_finder` over `model` for at most
+    /// `budget` more steps. A `cycle` already found ends it at once, after
+    /// the first [`run`](Self::run) has measured its activity.
+    pub fn new(
+        model: Model,
+        cycle_finder: StreamingCycleFinder,
+        cycle: Option<Range<usize>>,
+        budget: usize,
+    ) -> Self {
+        Self {
+            limit: model.timestep.saturating_add(budget),
+            model,
+            cycle_finder,
+            cycle,
+            cycle_activity: None,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.cycle.is_some() || self.model.timestep >= self.limit
+    }
+
+    /// Whether the budget ran out before the cycle was found.
+    pub fn is_exhausted(&self) -> bool {
+        self.cycle.is_none() && self.model.timestep >= self.limit
+    }
+
+    /// Takes up to `steps` steps, returning whether the search is done.
+    pub fn run(&mut self, steps: usize) -> bool {
+        for _ in 0..steps {
+            if self.is_done() {
+                break;
+            }
+
+            let state_hash = self.model.step();
+            self.cycle = self.cycle_finder.check_next(state_hash);
+        }
+
+        if let (Some(cycle), None) = (&self.cycle, &self.cycle_activity) {
+            self.cycle_activity = Some(self.cycle_finder.cycle_activity(cycle));
+        }
+
+        self.is_done()
+    }
+
+    pub fn request(&self) -> Request {
+        let mut snapshot = Snapshot::new(
+            &GeneratorParams::default(),
+            0,
+            &self.model,
+            &self.model,
+            &self.cycle_finder,
+            &self.cycle,
+            false,
+        );
+        // Only the compute trajectory is searched.
+        snapshot.display.history = History::new(1, 1);
+
+        Request {
+            snapshot,
+            limit: self.limit,
+        }
+    }
+
+    pub fn progress(&self) -> Progress {
+        Progress {
+            compute: Trajectory::new(&self.model),
+            cycle_finder: self.cycle_finder.cycle_finder.clone(),
+            cycle: self.cycle.clone(),
+            cycle_activity: self.cycle_activity.clone(),
+        }
+    }
+
+    /// Brings this search, the one `progress` was requested for, up to date.
+    pub fn apply(&mut self, progress: Progress) -> Result<(), String> {
+        let Trajectory {
+            timestep,
+            states,
+            history,
+        } = progress.compute;
+
+        if states.len() != self.model.graph.node_count() {
+            return Err(format!(
+                "expected {} node states, found {}",
+                self.model.graph.node_count(),
+                states.len()
+            ));
+        }
+
+        for (weight, state) in self.model.graph.node_weights_mut().zip(states.chars()) {
+            let state = match state {
+                '0' => false,
+                '1' => true,
+                _ => return Err(format!("invalid node state {:?}", state)),
+            };
+
+            weight.kind = weight.kind.with_state(state);
+        }
+
+        self.model.timestep = timestep;
+        self.model.history = history;
+        self.model.invalidate();
+
+        self.cycle_finder.cycle_finder = progress.cycle_finder;
+        self.cycle = progress.cycle;
+        self.cycle_activity = progress.cycle_activity;
+
+        Ok(())
+    }
+}
+
+impl Request {
+    pub fn into_search(self) -> Result<Search, String> {
+        let (model, _, cycle_finder) = self.snapshot.restore()?;
+
+        Ok(Search {
+            limit: self.limit,
+            ..Search::new(model, cycle_finder, self.snapshot.cycle, 0)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::model::generate;
+
+    fn states(model: &Model) -> Vec<bool> {
+        model
+            .graph
+            .raw_nodes()
+            .iter()
+            .map(|node| node.weight.kind.state())
+            .collect()
+    }
+
+    #[test]
+    fn remote_search_matches_local() {
+        for seed in 0..20 {
+            let params = GeneratorParams {
+                node_count: 50,
+                ..Default::default()
+            };
+            let model = generate::build(&params, &mut StdRng::seed_from_u64(seed));
+            let cycle_finder = StreamingCycleFinder::new(&model);
+            let mut local = Search::new(model, cycle_finder, None, 10_000);
+
+            let json = serde_json::to_string(&local.request()).unwrap();
+            let mut remote = serde_json::from_str::<Request>(&json)
+                .unwrap()
+                .into_search()
+                .unwrap();
+
+            let mut mirror = local.clone();
+            while !remote.run(100) {
+                let json = serde_json::to_string(&remote.progress()).unwrap();
+                mirror.apply(serde_json::from_str(&json).unwrap()).unwrap();
+            }
+            let json = serde_json::to_string(&remote.progress()).unwrap();
+            mirror.apply(serde_json::from_str(&json).unwrap()).unwrap();
+
+            while !local.run(100) {}
+
+            assert_eq!(mirror.model.timestep, local.model.timestep);
+            assert_eq!(states(&mirror.model), states(&local.model));
+            assert_eq!(mirror.cycle, local.cycle);
+            assert_eq!(mirror.cycle_activity, local.cycle_activity);
+            assert_eq!(mirror.cycle_finder.start(), local.cycle_finder.start());
+            assert_eq!(mirror.is_done(), local.is_done());
+        }
+    }
+}
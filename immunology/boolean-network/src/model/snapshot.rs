@@ -0,0 +1,382 @@
+//! Versioned save files for a running simulation.
+//!
+//! A snapshot is a JSON document holding the network once, plus the node
+//! states, timestep and history of each trajectory stepped over it, so that a
+//! restored simulation continues exactly where the saved one left off. Node
+//! states are strings with one `0` or `1` per node, in node index order.
+
+use std::{
+    io::{Read, Write},
+    ops::Range,
+};
+
+use bevy::math::Vec3;
+use petgraph::{graph::NodeIndex, visit::IntoNodeReferences};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    cycle::CycleFinder,
+    generate::GeneratorParams,
+    history::{History, StreamingCycleFinder},
+    search, Model, NodeKind, NodeWeight,
+};
+
+/// Incremented whenever the format changes incompatibly.
+pub const VERSION: u32 = 1;
+
+/// Most steps a saved cycle search may have taken, to the end of its cycle
+/// once found. Restoring one replays up to that many to locate and measure
+/// its cycle, so longer searches are saved restarted.
+pub const MAX_SEARCH_STEPS: usize = 100 * search::DEFAULT_BUDGET;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Node {
+    pub name: String,
+    /// Gate and the state the cycle search started from.
+    pub kind: NodeKind,
+    pub position: Vec3,
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Trajectory {
+    pub timestep: usize,
+    pub states: String,
+    pub history: History,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    /// Parameters and seed the network was generated from.
+    pub params: GeneratorParams,
+    pub seed: u64,
+    pub nodes: Vec<Node>,
+    /// `[source, target]` node indices.
+    pub edges: Vec<[u32; 2]>,
+    pub compute: Trajectory,
+    pub display: Trajectory,
+    pub cycle_finder: CycleFinder<u64>,
+    /// Timestep the cycle search started from.
+    #[serde(default)]
+    pub search_start: usize,
+    pub cycle: Option<Range<usize>>,
+    /// Whether node states were changed by hand.
+    #[serde(default)]
+    pub perturbed: bool,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+impl Trajectory {
+    pub(super) fn new(model: &Model) -> Self {
+        Self {
+            timestep: model.timestep,
+            states: model
+                .graph
+                .node_references()
+                .map(|(_, weight)| if weight.kind.state() { '1' } else { '0' })
+                .collect(),
+            history: model.history.clone(),
+        }
+    }
+}
+
+impl Snapshot {
+    /// `compute` and `display` must be trajectories of the same network, and
+    /// `cycle_finder` must be searching the former.
+    pub fn new(
+        params: &GeneratorParams,
+        seed: u64,
+        compute: &Model,
+        display: &Model,
+        cycle_finder: &StreamingCycleFinder,
+        cycle: &Option<Range<usize>>,
+        perturbed: bool,
+    ) -> Self {
+        // A search too long for a reader to replay is saved restarted from the
+        // compute model, which soon finds any cycle again.
+        let restarted;
+        let (cycle_finder, cycle) =
+            if replay_steps(cycle_finder.start(), compute.timestep, cycle) > MAX_SEARCH_STEPS {
+                restarted = StreamingCycleFinder::new(compute);
+                (&restarted, &None)
+            } else {
+                (cycle_finder, cycle)
+            };
+
+        // The search's initial model holds the states it started from, but
+        // layouts since then only moved the live models' nodes.
+        let graph = &cycle_finder.initial.graph;
+
+        Self {
+            version: VERSION,
+            params: params.clone(),
+            seed,
+            nodes: graph
+                .node_references()
+                .zip(compute.graph.raw_nodes())
+                .map(|((_, weight), live)| Node {
+                    name: weight.name.clone(),
+                    kind: weight.kind,
+                    position: live.weight.position,
+                    pinned: weight.pinned,
+                })
+                .collect(),
+            edges: graph
+                .raw_edges()
+                .iter()
+                .map(|edge| [edge.source().index() as u32, edge.target().index() as u32])
+                .collect(),
+            compute: Trajectory::new(compute),
+            display: Trajectory::new(display),
+            cycle_finder: cycle_finder.cycle_finder.clone(),
+            search_start: cycle_finder.start(),
+            cycle: cycle.clone(),
+            perturbed,
+        }
+    }
+
+    fn model(&self, trajectory: &Trajectory) -> Result<Model, String> {
+        if trajectory.states.len() != self.nodes.len() {
+            return Err(format!(
+                "expected {} node states, found {}",
+                self.nodes.len(),
+                trajectory.states.len()
+            ));
+        }
+
+        trajectory.history.validate(self.nodes.len())?;
+
+        let mut model = Model::new();
+
+        for (node, state) in self.nodes.iter().zip(trajectory.states.chars()) {
+            let state = match state {
+                '0' => false,
+                '1' => true,
+                _ => return Err(format!("invalid node state {:?}", state)),
+            };
+
+            model.graph.add_node(NodeWeight {
+                name: node.name.clone(),
+                kind: node.kind.with_state(state),
+                position: node.position,
+                pinned: node.pinned,
+            });
+        }
+
+        for &[source, target] in &self.edges {
+            if source as usize >= self.nodes.len() || target as usize >= self.nodes.len() {
+                return Err(format!("edge {} -> {} out of range", source, target));
+            }
+
+            model.graph.add_edge(
+                NodeIndex::new(source as usize),
+                NodeIndex::new(target as usize),
+                (),
+            );
+        }
+
+        model.timestep = trajectory.timestep;
+        model.history = trajectory.history.clone();
+
+        Ok(model)
+    }
+
+    /// Rebuilds the compute model, display model and cycle finder, checking
+    /// that they are consistent, as a file from elsewhere might not be.
+    pub fn restore(&self) -> Result<(Model, Model, StreamingCycleFinder), String> {
+        let steps = self
+            .compute
+            .timestep
+            .checked_sub(self.search_start)
+            .ok_or_else(|| {
+                format!(
+                    "cycle search from timestep {} after timestep {}",
+                    self.search_start, self.compute.timestep
+                )
+            })?;
+
+        self.cycle_finder.validate(steps)?;
+
+        if let Some(cycle) = &self.cycle {
+            if cycle.start < self.search_start
+                || cycle.start >= cycle.end
+                || cycle.end > self.compute.timestep
+            {
+                return Err(format!(
+                    "cycle {:?} outside the search from timestep {} to {}",
+                    cycle, self.search_start, self.compute.timestep
+                ));
+            }
+        }
+
+        if replay_steps(self.search_start, self.compute.timestep, &self.cycle) > MAX_SEARCH_STEPS {
+            return Err(format!(
+                "cycle search of more than {} steps",
+                MAX_SEARCH_STEPS
+            ));
+        }
+
+        let initial = Trajectory {
+            timestep: self.search_start,
+            states: self
+                .nodes
+                .iter()
+                .map(|node| if node.kind.state() { '1' } else { '0' })
+                .collect(),
+            history: History::new(1, 1),
+        };
+
+        Ok((
+            self.model(&self.compute)?,
+            self.model(&self.display)?,
+            StreamingCycleFinder {
+                initial: self.model(&initial)?,
+                cycle_finder: self.cycle_finder.clone(),
+            },
+        ))
+    }
+
+    pub fn write(&self, writer: impl Write) -> Result<(), String> {
+        serde_json::to_writer(writer, self).map_err(|err| err.to_string())
+    }
+
+    pub fn read(reader: impl Read) -> Result<Self, String> {
+        let value = serde_json::from_reader::<_, serde_json::Value>(reader)
+            .map_err(|err| format!("invalid snapshot: {}", err))?;
+
+        let header =
+            Header::deserialize(&value).map_err(|err| format!("invalid snapshot: {}", err))?;
+        if header.version != VERSION {
+            return Err(format!(
+                "unsupported snapshot version {} (expected {})",
+                header.version, VERSION
+            ));
+        }
+
+        Self::deserialize(value).map_err(|err| format!("invalid snapshot: {}", err))
+    }
+}
+
+/// Steps a search from `search_start` replays to measure its cycle, or to
+/// find μ once it has found λ at `timestep` or later.
+fn replay_steps(search_start: usize, timestep: usize, cycle: &Option<Range<usize>>) -> usize {
+    cycle
+        .as_ref()
+        .map_or(timestep, |cycle| cycle.end)
+        .saturating_sub(search_start)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::model::generate;
+
+    // A searched network whose display model keeps node states, as saved.
+    fn saved() -> Value {
+        let params = GeneratorParams {
+            node_count: 100,
+            ..Default::default()
+        };
+        let mut compute = generate::kauffman(&params, 2, &mut StdRng::seed_from_u64(0));
+        let mut cycle_finder = StreamingCycleFinder::new(&compute);
+        let mut display = compute.clone();
+        display.history = History::new(16, 4).with_states();
+
+        let mut cycle = None;
+        while cycle.is_none() {
+            cycle = cycle_finder.check_next(compute.step());
+            display.step();
+        }
+
+        let snapshot = Snapshot::new(&params, 0, &compute, &display, &cycle_finder, &cycle, false);
+        serde_json::to_value(&snapshot).unwrap()
+    }
+
+    fn restore(value: &Value) -> Result<(Model, Model, StreamingCycleFinder), String> {
+        Snapshot::read(value.to_string().as_bytes())?.restore()
+    }
+
+    #[test]
+    fn restores_saved() {
+        let value = saved();
+        let (compute, display, cycle_finder) = restore(&value).unwrap();
+
+        assert_eq!(compute.timestep, value["compute"]["timestep"]);
+        assert_eq!(display.timestep, value["display"]["timestep"]);
+        assert_eq!(cycle_finder.start(), 0);
+    }
+
+    #[test]
+    fn restarts_long_search() {
+        let params = GeneratorParams {
+            node_count: 10,
+            ..Default::default()
+        };
+        let mut compute = generate::kauffman(&params, 2, &mut StdRng::seed_from_u64(0));
+        let cycle_finder = StreamingCycleFinder::new(&compute);
+        compute.timestep = MAX_SEARCH_STEPS + 10;
+
+        for cycle in &[
+            None,
+            Some(5..8),
+            Some(MAX_SEARCH_STEPS + 1..MAX_SEARCH_STEPS + 3),
+        ] {
+            let snapshot =
+                Snapshot::new(&params, 0, &compute, &compute, &cycle_finder, cycle, false);
+            let (_, _, restored) = snapshot.restore().unwrap();
+
+            match cycle {
+                Some(cycle) if cycle.end <= MAX_SEARCH_STEPS => {
+                    assert_eq!(
+                        (restored.start(), &snapshot.cycle),
+                        (0, &Some(cycle.clone()))
+                    )
+                }
+                _ => assert_eq!(
+                    (restored.start(), &snapshot.cycle),
+                    (compute.timestep, &None)
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_inconsistent() {
+        let corruptions: [fn(&mut Value); 10] = [
+            |value| value["display"]["history"]["states"][0] = json!([0]),
+            |value| value["display"]["history"]["states"][0] = json!([0, 0, 0]),
+            |value| value["display"]["history"]["state_hashes"] = json!([]),
+            |value| {
+                let len = value["compute"]["history"]["state_hashes"]
+                    .as_array()
+                    .unwrap()
+                    .len();
+                value["compute"]["history"]["hamming_distances"] = json!(vec![0; len + 1]);
+            },
+            |value| value["compute"]["history"]["capacity"] = json!(0),
+            |value| value["display"]["history"]["capacity"] = json!(1),
+            |value| value["cycle"] = json!({ "start": 0, "end": 100000 }),
+            |value| value["cycle_finder"]["power"] = json!(3),
+            |value| value["search_start"] = json!(100000),
+            |value| {
+                value["compute"]["timestep"] = json!(MAX_SEARCH_STEPS + 1);
+                value["cycle"] = Value::Null;
+            },
+        ];
+
+        for corrupt in corruptions.iter() {
+            let mut value = saved();
+            corrupt(&mut value);
+            assert!(restore(&value).is_err(), "{}", value);
+        }
+    }
+}
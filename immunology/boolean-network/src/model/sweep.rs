@@ -0,0 +1,237 @@
+use std::{io::Write, str::FromStr};
+
+use petgraph::{graph::NodeIndex, visit::IntoNodeReferences};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
+
+use super::{
+    ensemble::{Ensemble, EnsembleConfig},
+    generate::{self, Param},
+    Model,
+};
+
+/// Evenly spaced values of one generator parameter.
+#[derive(Debug, Clone)]
+pub struct Axis {
+    pub param: Param,
+    pub values: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Metric {
+    /// Mean attractor length λ over the ensemble.
+    CycleLength,
+    /// Mean Hamming distance after one step between states that differ in a
+    /// single node; above 1 perturbations spread (chaos), below 1 they die out
+    /// (order).
+    DerridaSlope,
+}
+
+pub struct Sweep {
+    pub x: Axis,
+    pub y: Axis,
+    pub metric: Metric,
+    /// Base ensemble whose generator parameters are overridden at each point.
+    pub ensemble: EnsembleConfig,
+}
+
+impl FromStr for Axis {
+    type Err = String;
+
+    /// Parses `param:start:end:steps`, e.g. `connect-dist:3:8:11`.
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let parts = spec.split(':').collect::<Vec<_>>();
+
+        if parts.len() != 4 {
+            return Err(format!("expected param:start:end:steps, found {:?}", spec));
+        }
+
+        let number = |s: &str| {
+            s.parse::<f64>()
+                .map_err(|_| format!("invalid number {:?} in {:?}", s, spec))
+        };
+
+        let (start, end, steps) = (number(parts[1])?, number(parts[2])?, number(parts[3])?);
+        let steps = steps as usize;
+
+        Ok(Self {
+            param: parts[0].parse()?,
+            values: (0..steps)
+                .map(|i| match steps {
+                    1 => start,
+                    _ => start + (end - start) * i as f64 / (steps - 1) as f64,
+                })
+                .collect(),
+        })
+    }
+}
+
+impl FromStr for Metric {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "cycle-length" => Ok(Metric::CycleLength),
+            "derrida" => Ok(Metric::DerridaSlope),
+            _ => Err(format!("unknown metric {:?}", name)),
+        }
+    }
+}
+
+impl Sweep {
+    /// Returns the metric at every grid point, indexed `[x][y]`. Fails before
+    /// measuring anything if the parameters at any point are invalid.
+    pub fn run(&self) -> Result<Vec<Vec<f64>>, String> {
+        let grid = self
+            .x
+            .values
+            .iter()
+            .map(|&x| {
+                self.y
+                    .values
+                    .iter()
+                    .map(|&y| {
+                        let mut config = self.ensemble.clone();
+                        config.params.set(self.x.param, x);
+                        config.params.set(self.y.param, y);
+
+                        config.params.validate().map_err(|err| {
+                            format!(
+                                "at {:?} {}, {:?} {}: {}",
+                                self.x.param, x, self.y.param, y, err
+                            )
+                        })?;
+
+                        Ok(config)
+                    })
+                    .collect::<Result<Vec<_>, String>>()
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(grid
+            .iter()
+            .map(|row| row.iter().map(|config| self.measure(config)).collect())
+            .collect())
+    }
+
+    fn measure(&self, config: &EnsembleConfig) -> f64 {
+        match self.metric {
+            Metric::CycleLength => Ensemble::run(config).summary().lambda.mean,
+            Metric::DerridaSlope => {
+                let slopes = (0..config.networks)
+                    .into_par_iter()
+                    .map(|network| {
+                        let mut rng =
+                            StdRng::seed_from_u64(config.seed.wrapping_add(network as u64));
+                        let model = generate::build(&config.params, &mut rng);
+
+                        derrida_slope(
+                            &model,
+                            config.params.active_prob,
+                            config.initial_states,
+                            &mut rng,
+                        )
+                    })
+                    .collect::<Vec<_>>();
+
+                (slopes.len() as f64).recip() * slopes.iter().sum::<f64>()
+            }
+        }
+    }
+
+    /// Writes the grid as a matrix with x values down the first column and y
+    /// values along the header row.
+    pub fn write_csv(&self, grid: &[Vec<f64>], mut writer: impl Write) -> std::io::Result<()> {
+        write!(writer, "{:?}\\{:?}", self.x.param, self.y.param)?;
+        for y in &self.y.values {
+            write!(writer, ",{}", y)?;
+        }
+        writeln!(writer)?;
+
+        for (x, row) in self.x.values.iter().zip(grid) {
+            write!(writer, "{}", x)?;
+            for value in row {
+                write!(writer, ",{}", value)?;
+            }
+            writeln!(writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn states(model: &Model) -> impl Iterator<Item = bool> + '_ {
+    model
+        .graph
+        .node_references()
+        .map(|(_, weight)| weight.kind.state())
+}
+
+/// Estimates the slope of the Derrida map at the origin from `samples` random
+/// states, each compared against a copy with one random node flipped.
+pub fn derrida_slope(model: &Model, active_prob: f64, samples: usize, rng: &mut impl Rng) -> f64 {
+    let node_count = model.graph.node_count();
+
+    if node_count == 0 || samples == 0 {
+        return f64::NAN;
+    }
+
+    let total = (0..samples)
+        .map(|_| {
+            let mut original = model.clone();
+            generate::randomize_states(&mut original, active_prob, rng);
+
+            let mut perturbed = original.clone();
+            let flipped = perturbed
+                .graph
+                .node_weight_mut(NodeIndex::new(rng.gen_range(0..node_count)))
+                .unwrap();
+            flipped.kind = flipped.kind.with_state(!flipped.kind.state());
+
+            original.step();
+            perturbed.step();
+
+            states(&original)
+                .zip(states(&perturbed))
+                .filter(|(a, b)| a != b)
+                .count()
+        })
+        .sum::<usize>();
+
+    total as f64 / samples as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::generate::GeneratorParams;
+
+    fn sweep(x: &str, y: &str) -> Sweep {
+        Sweep {
+            x: x.parse().unwrap(),
+            y: y.parse().unwrap(),
+            metric: Metric::DerridaSlope,
+            ensemble: EnsembleConfig {
+                params: GeneratorParams {
+                    node_count: 20,
+                    gate_mix: [1.0, 1.0, 0.0],
+                    ..Default::default()
+                },
+                networks: 2,
+                initial_states: 2,
+                max_steps: 100,
+                seed: 0,
+            },
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_points() {
+        assert!(sweep("active-prob:0:1.5:4", "nodes:10:20:2").run().is_err());
+        assert!(sweep("and:0:1:2", "or:0:1:2").run().is_err());
+        assert!(sweep("nodes:0:10:2", "active-prob:0:1:2").run().is_err());
+
+        let grid = sweep("active-prob:0:1:3", "nodes:10:20:2").run().unwrap();
+        assert_eq!((grid.len(), grid[0].len()), (3, 2));
+    }
+}
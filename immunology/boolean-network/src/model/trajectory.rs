@@ -0,0 +1,334 @@
+//! Export of a model's recorded history for analysis in other tools.
+//!
+//! The model's [`History`] must record node states (see
+//! [`History::with_states`]); every retained timestep is written. `cycle` is
+//! the range found by the cycle finder, from which each timestep is marked as
+//! `transient` (before μ) or `attractor` (from μ on), or left blank while the
+//! search is still running.
+//!
+//! - `long`: CSV with columns `timestep,node,name,state,p,phase`, one row per
+//!   node per timestep.
+//! - `wide`: CSV with columns `timestep,p,phase` followed by one `0`/`1` column
+//!   per node, headed by its name.
+//! - `rle`: a binary columnar file in which each node's states over time are
+//!   run-length encoded, so that frozen nodes cost a few bytes whatever the
+//!   length of the run. All integers and floats are little-endian:
+//!
+//! | field   | type                             | contents                                  |
+//! |---------|----------------------------------|-------------------------------------------|
+//! | magic   | `[u8; 4]`                        | `BNRL`                                    |
+//! | version | `u32`                            | `1`                                       |
+//! | nodes   | `u32`                            | node count N                              |
+//! | steps   | `u32`                            | timestep count T                          |
+//! | first   | `u64`                            | timestep of the first row                 |
+//! | mu      | `u64`                            | μ, or `u64::MAX` if no cycle was found    |
+//! | lambda  | `u64`                            | λ, or `0` if no cycle was found           |
+//! | names   | N × (`u32` length, UTF-8 bytes)  | node names                                |
+//! | p       | T × `f32`                        | fraction of active nodes                  |
+//! | hashes  | T × `u64`                        | state hashes                              |
+//! | columns | N × (`u32` count R, R × `u32`)   | run lengths, alternating from state `0`   |
+//!
+//! The first run of a column is inactive and may be empty; the runs of each
+//! column sum to T.
+//!
+//! In the CSV formats, names containing commas, quotes or line breaks are
+//! quoted as in RFC 4180.
+
+use std::{
+    borrow::Cow,
+    io::{self, Write},
+    ops::Range,
+    str::FromStr,
+};
+
+use petgraph::visit::IntoNodeReferences;
+
+use super::{history::History, Model};
+
+const RLE_MAGIC: &[u8; 4] = b"BNRL";
+const RLE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Format {
+    Long,
+    Wide,
+    Rle,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "long" => Ok(Format::Long),
+            "wide" => Ok(Format::Wide),
+            "rle" => Ok(Format::Rle),
+            _ => Err(format!("unknown trajectory format {:?}", name)),
+        }
+    }
+}
+
+struct Row<'a> {
+    timestep: usize,
+    state_hash: u64,
+    p: f32,
+    states: &'a [u64],
+}
+
+impl Row<'_> {
+    fn state(&self, node: usize) -> bool {
+        self.states[node / 64] >> (node % 64) & 1 == 1
+    }
+}
+
+fn rows(history: &History) -> Result<Vec<Row<'_>>, String> {
+    let states = history
+        .states()
+        .ok_or("the history does not record node states")?;
+    // Recording may have started after the history did.
+    let skip = history.len() - states.len();
+
+    Ok(history
+        .timesteps()
+        .zip(history.state_hashes())
+        .zip(history.p_values())
+        .skip(skip)
+        .zip(states)
+        .map(|(((timestep, &state_hash), &p), states)| Row {
+            timestep,
+            state_hash,
+            p,
+            states,
+        })
+        .collect())
+}
+
+fn phase(cycle: &Option<Range<usize>>, timestep: usize) -> &'static str {
+    match cycle {
+        Some(cycle) if timestep < cycle.start => "transient",
+        Some(_) => "attractor",
+        None => "",
+    }
+}
+
+/// `field` quoted as RFC 4180 requires when it contains a delimiter, quote or
+/// line break.
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+pub fn write(
+    format: Format,
+    model: &Model,
+    cycle: &Option<Range<usize>>,
+    writer: impl Write,
+) -> Result<(), String> {
+    let rows = rows(&model.history)?;
+
+    match format {
+        Format::Long => write_long(model, &rows, cycle, writer),
+        Format::Wide => write_wide(model, &rows, cycle, writer),
+        Format::Rle => write_rle(model, &rows, cycle, writer),
+    }
+    .map_err(|err| err.to_string())
+}
+
+fn write_long(
+    model: &Model,
+    rows: &[Row],
+    cycle: &Option<Range<usize>>,
+    mut writer: impl Write,
+) -> io::Result<()> {
+    writeln!(writer, "timestep,node,name,state,p,phase")?;
+
+    for row in rows {
+        for (node, weight) in model.graph.node_references() {
+            writeln!(
+                writer,
+                "{},{},{},{},{},{}",
+                row.timestep,
+                node.index(),
+                csv_field(&weight.name),
+                row.state(node.index()) as u8,
+                row.p,
+                phase(cycle, row.timestep)
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_wide(
+    model: &Model,
+    rows: &[Row],
+    cycle: &Option<Range<usize>>,
+    mut writer: impl Write,
+) -> io::Result<()> {
+    write!(writer, "timestep,p,phase")?;
+    for (_, weight) in model.graph.node_references() {
+        write!(writer, ",{}", csv_field(&weight.name))?;
+    }
+    writeln!(writer)?;
+
+    for row in rows {
+        write!(
+            writer,
+            "{},{},{}",
+            row.timestep,
+            row.p,
+            phase(cycle, row.timestep)
+        )?;
+        for node in 0..model.graph.node_count() {
+            write!(writer, ",{}", row.state(node) as u8)?;
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+fn write_rle(
+    model: &Model,
+    rows: &[Row],
+    cycle: &Option<Range<usize>>,
+    mut writer: impl Write,
+) -> io::Result<()> {
+    writer.write_all(RLE_MAGIC)?;
+    writer.write_all(&RLE_VERSION.to_le_bytes())?;
+    writer.write_all(&(model.graph.node_count() as u32).to_le_bytes())?;
+    writer.write_all(&(rows.len() as u32).to_le_bytes())?;
+
+    let first = rows.first().map_or(model.timestep, |row| row.timestep);
+    writer.write_all(&(first as u64).to_le_bytes())?;
+
+    let (mu, lambda) = match cycle {
+        Some(cycle) => (cycle.start as u64, cycle.len() as u64),
+        None => (u64::MAX, 0),
+    };
+    writer.write_all(&mu.to_le_bytes())?;
+    writer.write_all(&lambda.to_le_bytes())?;
+
+    for (_, weight) in model.graph.node_references() {
+        writer.write_all(&(weight.name.len() as u32).to_le_bytes())?;
+        writer.write_all(weight.name.as_bytes())?;
+    }
+
+    for row in rows {
+        writer.write_all(&row.p.to_le_bytes())?;
+    }
+    for row in rows {
+        writer.write_all(&row.state_hash.to_le_bytes())?;
+    }
+
+    let mut runs = Vec::new();
+
+    for node in 0..model.graph.node_count() {
+        runs.clear();
+
+        let (mut current, mut length) = (false, 0u32);
+        for row in rows {
+            if row.state(node) != current {
+                runs.push(length);
+                current = !current;
+                length = 0;
+            }
+            length += 1;
+        }
+        runs.push(length);
+
+        writer.write_all(&(runs.len() as u32).to_le_bytes())?;
+        for run in &runs {
+            writer.write_all(&run.to_le_bytes())?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec3;
+
+    use super::*;
+    use crate::model::{NodeKind, NodeWeight};
+
+    const NAMES: [&str; 4] = ["plain", "a,b", "say \"hi\"", "two\nlines"];
+
+    fn model() -> Model {
+        let mut model = Model::new();
+        model.history = History::new(16, 4).with_states();
+
+        for (index, name) in NAMES.iter().enumerate() {
+            model.graph.add_node(NodeWeight {
+                name: name.to_string(),
+                kind: NodeKind::Nor(index % 2 == 0),
+                position: Vec3::ZERO,
+                pinned: false,
+            });
+        }
+
+        for _ in 0..3 {
+            model.step();
+        }
+
+        model
+    }
+
+    // A minimal RFC 4180 reader.
+    fn parse_csv(text: &str) -> Vec<Vec<String>> {
+        let mut records = vec![];
+        let mut record = vec![];
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = !quoted,
+                ',' if !quoted => record.push(std::mem::take(&mut field)),
+                '\n' if !quoted => {
+                    record.push(std::mem::take(&mut field));
+                    records.push(std::mem::take(&mut record));
+                }
+                c => field.push(c),
+            }
+        }
+
+        records
+    }
+
+    fn write_csv(format: Format) -> Vec<Vec<String>> {
+        let mut out = Vec::new();
+        write(format, &model(), &None, &mut out).unwrap();
+        parse_csv(&String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn long_quotes_names() {
+        let records = write_csv(Format::Long);
+
+        assert_eq!(records.len(), 1 + 4 * NAMES.len());
+        for (index, record) in records[1..].iter().enumerate() {
+            assert_eq!(record.len(), 6);
+            assert_eq!(record[2], NAMES[index % NAMES.len()]);
+        }
+    }
+
+    #[test]
+    fn wide_quotes_names() {
+        let records = write_csv(Format::Wide);
+
+        assert_eq!(records.len(), 1 + 4);
+        assert_eq!(records[0][3..], NAMES);
+        assert!(records.iter().all(|record| record.len() == 3 + NAMES.len()));
+    }
+}
@@ -0,0 +1,145 @@
+//! Colouring of the scene by the attractor once the cycle is known: nodes by
+//! how often they are active over it, with the frozen part of the network
+//! faded and the wires among oscillating nodes highlighted.
+
+use bevy::prelude::*;
+
+use crate::{MaterialHandles, ModelState, SimEdge};
+
+const HEAT_LEVELS: usize = 16;
+
+#[derive(Default)]
+pub struct Overlay {
+    pub enabled: bool,
+}
+
+pub struct OverlayButton;
+
+pub struct OverlayMaterials {
+    /// From rarely to mostly active.
+    heat: Vec<Handle<StandardMaterial>>,
+    frozen_inactive: Handle<StandardMaterial>,
+    frozen_active: Handle<StandardMaterial>,
+    wire_core: Handle<StandardMaterial>,
+    wire_frozen: Handle<StandardMaterial>,
+}
+
+impl FromWorld for OverlayMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world
+            .get_resource_mut::<Assets<StandardMaterial>>()
+            .unwrap();
+
+        OverlayMaterials {
+            heat: (0..HEAT_LEVELS)
+                .map(|level| {
+                    let activity = level as f32 / (HEAT_LEVELS - 1) as f32;
+                    materials.add(Color::hsla(240.0 * (1.0 - activity), 0.9, 0.55, 0.85).into())
+                })
+                .collect(),
+            frozen_inactive: materials.add(Color::rgba(0.5, 0.5, 0.55, 0.08).into()),
+            frozen_active: materials.add(Color::rgba(0.8, 0.75, 0.5, 0.25).into()),
+            wire_core: materials.add(Color::rgb(1.0, 0.45, 0.2).into()),
+            wire_frozen: materials.add(Color::rgba(0.5, 0.5, 0.5, 0.2).into()),
+        }
+    }
+}
+
+impl OverlayMaterials {
+    /// Material and scale of a node active in `count` of the cycle's `period`
+    /// steps.
+    pub fn bulb(&self, count: usize, period: usize) -> (Handle<StandardMaterial>, f32) {
+        if count == 0 {
+            (self.frozen_inactive.clone(), 0.7)
+        } else if count == period {
+            (self.frozen_active.clone(), 0.7)
+        } else {
+            let activity = count as f32 / period as f32;
+            let level = (activity * (HEAT_LEVELS - 1) as f32).round() as usize;
+            (self.heat[level].clone(), 1.3)
+        }
+    }
+}
+
+/// The cycle's period and per-node activity over it, if the overlay is shown
+/// and the cycle is known.
+pub fn attractor<'a>(overlay: &Overlay, model: &'a ModelState) -> Option<(usize, &'a [usize])> {
+    if !overlay.enabled {
+        return None;
+    }
+
+    Some((
+        model.cycle.as_ref()?.len(),
+        model.cycle_activity.as_deref()?,
+    ))
+}
+
+pub fn overlay_button(
+    interactions: Query<&Interaction, (Changed<Interaction>, With<OverlayButton>)>,
+    mut overlay: ResMut<Overlay>,
+) {
+    for interaction in interactions.iter() {
+        if let Interaction::Clicked = interaction {
+            overlay.enabled = !overlay.enabled;
+        }
+    }
+}
+
+pub fn overlay_button_label(
+    overlay: Res<Overlay>,
+    buttons: Query<&Children, With<OverlayButton>>,
+    mut texts: Query<&mut Text>,
+) {
+    if !overlay.is_changed() {
+        return;
+    }
+
+    for children in buttons.iter() {
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value = if overlay.enabled {
+                    "Heat: on"
+                } else {
+                    "Heat: off"
+                }
+                .to_string();
+            }
+        }
+    }
+}
+
+pub fn wire_overlay(
+    overlay: Res<Overlay>,
+    overlay_materials: Res<OverlayMaterials>,
+    material_handles: Res<MaterialHandles>,
+    model_opt: Option<Res<ModelState>>,
+    mut wires: Query<(&SimEdge, &mut Handle<StandardMaterial>)>,
+) {
+    let model = if let Some(model) = model_opt {
+        model
+    } else {
+        return;
+    };
+
+    let attractor = attractor(&overlay, &model);
+    let edges = model.display_model.graph.raw_edges();
+
+    for (wire, mut material) in wires.iter_mut() {
+        *material = match (attractor, edges.get(wire.graph_id as usize)) {
+            (Some((period, counts)), Some(edge)) => {
+                let oscillating = |node: usize| {
+                    counts
+                        .get(node)
+                        .map_or(false, |&count| count > 0 && count < period)
+                };
+
+                if oscillating(edge.source().index()) && oscillating(edge.target().index()) {
+                    overlay_materials.wire_core.clone()
+                } else {
+                    overlay_materials.wire_frozen.clone()
+                }
+            }
+            _ => material_handles.wire.clone(),
+        };
+    }
+}
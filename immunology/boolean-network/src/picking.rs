@@ -0,0 +1,145 @@
+//! Picking nodes and edges under the cursor by casting rays into the scene.
+
+use bevy::prelude::*;
+use petgraph::{
+    graph::{EdgeIndex, NodeIndex},
+    visit::IntoNodeReferences,
+};
+
+use crate::{model::Model, NetworkCamera};
+
+pub type Cameras<'a> =
+    Query<'a, (&'a GlobalTransform, &'a PerspectiveProjection), With<NetworkCamera>>;
+
+/// Whether the cursor is over a UI node, in which case clicks are not meant
+/// for the scene.
+pub fn over_ui(interactions: &Query<&Interaction>) -> bool {
+    interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+}
+
+/// Ray from the camera through the cursor, as an origin and unit direction.
+pub fn cursor_ray(windows: &Windows, cameras: &Cameras) -> Option<(Vec3, Vec3)> {
+    let window = windows.get_primary()?;
+    let cursor = window.cursor_position()?;
+    let (camera, projection) = cameras.iter().next()?;
+
+    let ndc = cursor / Vec2::new(window.width(), window.height()) * 2.0 - Vec2::ONE;
+    let half_height = (projection.fov / 2.0).tan();
+    let direction = camera.rotation
+        * Vec3::new(
+            ndc.x * half_height * projection.aspect_ratio,
+            ndc.y * half_height,
+            -1.0,
+        )
+        .normalize();
+
+    Some((camera.translation, direction))
+}
+
+/// Distance along a ray with unit `direction` to where it enters a sphere.
+fn ray_sphere(origin: Vec3, direction: Vec3, center: Vec3, radius: f32) -> Option<f32> {
+    let offset = origin - center;
+    let b = offset.dot(direction);
+    let discriminant = b * b - (offset.length_squared() - radius * radius);
+
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    let distance = -b - discriminant.sqrt();
+    if distance >= 0.0 {
+        Some(distance)
+    } else {
+        None
+    }
+}
+
+/// Closest approach of a ray with unit `direction` to the segment from `a` to
+/// `b`.
+fn ray_segment(origin: Vec3, direction: Vec3, a: Vec3, b: Vec3) -> Option<f32> {
+    let segment = b - a;
+    let offset = origin - a;
+    let length_squared = segment.length_squared();
+
+    if length_squared < f32::EPSILON {
+        return None;
+    }
+
+    let cos = direction.dot(segment);
+    let denominator = length_squared - cos * cos;
+    let along = if denominator > f32::EPSILON {
+        (cos * segment.dot(offset) - direction.dot(offset) * length_squared) / denominator
+    } else {
+        0.0
+    };
+
+    let t = ((cos * along + segment.dot(offset)) / length_squared).clamp(0.0, 1.0);
+    let along = (cos * t - direction.dot(offset)).max(0.0);
+
+    Some((origin + direction * along).distance(a + segment * t))
+}
+
+/// Where a ray meets the horizontal plane at `height`.
+pub fn ray_plane(origin: Vec3, direction: Vec3, height: f32) -> Option<Vec3> {
+    if direction.y.abs() < f32::EPSILON {
+        return None;
+    }
+
+    let distance = (height - origin.y) / direction.y;
+    if distance >= 0.0 {
+        Some(origin + direction * distance)
+    } else {
+        None
+    }
+}
+
+/// Nearest node hit by a ray.
+pub fn pick_node(
+    model: &Model,
+    origin: Vec3,
+    direction: Vec3,
+    bulb_radius: f32,
+) -> Option<NodeIndex> {
+    model
+        .graph
+        .node_references()
+        .filter_map(|(node, weight)| {
+            let distance = ray_sphere(origin, direction, weight.position, bulb_radius)?;
+            Some((distance, node))
+        })
+        .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap())
+        .map(|(_, node)| node)
+}
+
+/// Edge passing nearest to a ray, within half a bulb radius.
+pub fn pick_edge(
+    model: &Model,
+    origin: Vec3,
+    direction: Vec3,
+    bulb_radius: f32,
+) -> Option<EdgeIndex> {
+    let graph = &model.graph;
+
+    graph
+        .raw_edges()
+        .iter()
+        .enumerate()
+        .filter_map(|(index, edge)| {
+            let gap = ray_segment(
+                origin,
+                direction,
+                graph[edge.source()].position,
+                graph[edge.target()].position,
+            )?;
+
+            if gap < bulb_radius * 0.5 {
+                Some((gap, EdgeIndex::new(index)))
+            } else {
+                None
+            }
+        })
+        .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap())
+        .map(|(_, edge)| edge)
+}
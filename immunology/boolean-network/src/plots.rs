@@ -0,0 +1,246 @@
+//! Plots of the displayed trajectory, drawn into textures shown by UI nodes.
+
+use std::ops::Range;
+
+use bevy::{
+    prelude::*,
+    render::texture::{Extent3d, TextureDimension, TextureFormat},
+};
+
+use crate::{
+    model::{history::History, raster::Raster},
+    ModelState,
+};
+
+/// Timesteps shown by the raster panel.
+const RASTER_COLUMNS: usize = 256;
+
+/// Most rows of the raster panel, well within GPU texture limits; larger
+/// networks are shown in bands of nodes.
+const RASTER_ROWS: usize = 2048;
+
+const CHART_WIDTH: usize = 512;
+const CHART_HEIGHT: usize = 160;
+const CHART_BACKGROUND: [u8; 4] = [16, 16, 20, 255];
+const CHART_P: [u8; 4] = [255, 215, 0, 255];
+const CHART_HAMMING: [u8; 4] = [90, 160, 255, 255];
+const CHART_ONSET: [u8; 4] = [220, 40, 40, 255];
+
+pub struct RasterPanel(Handle<Texture>);
+pub struct ChartPanel(Handle<Texture>);
+
+pub fn setup_plots(
+    mut commands: Commands,
+    mut textures: ResMut<Assets<Texture>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    asset_server: Res<AssetServer>,
+) {
+    let texture = textures.add(Texture::default());
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(512.0), Val::Px(256.0)),
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(10.0),
+                    bottom: Val::Px(10.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            material: materials.add(texture.clone().into()),
+            ..Default::default()
+        })
+        .insert(RasterPanel(texture));
+
+    let texture = textures.add(Texture::default());
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(CHART_WIDTH as f32), Val::Px(CHART_HEIGHT as f32)),
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(10.0),
+                    bottom: Val::Px(276.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            material: materials.add(texture.clone().into()),
+            ..Default::default()
+        })
+        .insert(ChartPanel(texture));
+
+    let font = asset_server.load("fonts/FiraCode-Medium.ttf");
+    let section = |value: &str, [r, g, b, _]: [u8; 4]| TextSection {
+        value: value.to_string(),
+        style: TextStyle {
+            font: font.clone(),
+            font_size: 24.0,
+            color: Color::rgb_u8(r, g, b),
+        },
+    };
+
+    commands.spawn_bundle(TextBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: Rect {
+                right: Val::Px(10.0),
+                bottom: Val::Px(446.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        text: Text {
+            sections: vec![
+                section("P  ", CHART_P),
+                section("flipped / N  ", CHART_HAMMING),
+                section("μ", CHART_ONSET),
+            ],
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+}
+
+/// Redraws the raster of node states (one row per node, one column per
+/// timestep) whenever the display model steps.
+pub fn update_raster(
+    model_opt: Option<Res<ModelState>>,
+    panels: Query<&RasterPanel>,
+    mut textures: ResMut<Assets<Texture>>,
+    mut drawn: Local<Option<(usize, usize, Option<Range<usize>>)>>,
+) {
+    let model = if let Some(model) = model_opt {
+        model
+    } else {
+        return;
+    };
+
+    let display_model = &model.display_model;
+    let key = (
+        display_model.timestep,
+        display_model.history.len(),
+        model.cycle.clone(),
+    );
+
+    if !model.is_added() && drawn.as_ref() == Some(&key) {
+        return;
+    }
+    *drawn = Some(key);
+
+    let raster = match Raster::new(display_model, &model.cycle, RASTER_COLUMNS) {
+        Ok(raster) if raster.rows > 0 && raster.columns > 0 => raster.with_max_rows(RASTER_ROWS),
+        _ => return,
+    };
+
+    let texture = Texture::new(
+        Extent3d::new(raster.columns as u32, raster.rows as u32, 1),
+        TextureDimension::D2,
+        raster.pixels(),
+        TextureFormat::Rgba8UnormSrgb,
+    );
+
+    for panel in panels.iter() {
+        if let Some(panel_texture) = textures.get_mut(&panel.0) {
+            *panel_texture = texture.clone();
+        }
+    }
+}
+
+/// Draws P and the fraction of nodes flipped on each step over the timesteps
+/// `history` retains, with the cycle onset μ marked. Each column covers an
+/// equal share of the timesteps and spans the values within it.
+fn chart_pixels(history: &History, node_count: usize, cycle: &Option<Range<usize>>) -> Vec<u8> {
+    let mut pixels = CHART_BACKGROUND.repeat(CHART_WIDTH * CHART_HEIGHT);
+    let len = history.len();
+
+    if len == 0 {
+        return pixels;
+    }
+
+    let mut draw = |x: usize, values: &mut dyn Iterator<Item = f32>, color: [u8; 4]| {
+        let (low, high) = values.fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), value| {
+            (low.min(value), high.max(value))
+        });
+
+        if low > high {
+            return;
+        }
+
+        let row = |value: f32| ((1.0 - value.clamp(0.0, 1.0)) * (CHART_HEIGHT - 1) as f32) as usize;
+        for y in row(high)..=row(low) {
+            pixels[(y * CHART_WIDTH + x) * 4..][..4].copy_from_slice(&color);
+        }
+    };
+
+    if let Some(cycle) = cycle {
+        if history.timesteps().contains(&cycle.start) {
+            let x = (cycle.start - history.timesteps().start) * CHART_WIDTH / len;
+            draw(x, &mut [0.0, 1.0].iter().copied(), CHART_ONSET);
+        }
+    }
+
+    let p_values = history.p_values();
+    let hamming_distances = history.hamming_distances();
+    let unrecorded = len - hamming_distances.len();
+
+    for x in 0..CHART_WIDTH {
+        // Starting from the previous column's last entry joins up the line.
+        let start = (x * len / CHART_WIDTH).saturating_sub(1);
+        let end = ((x + 1) * len / CHART_WIDTH).max(start + 1).min(len);
+
+        draw(
+            x,
+            &mut hamming_distances
+                .range(start.saturating_sub(unrecorded)..end.saturating_sub(unrecorded))
+                .map(|&distance| distance as f32 / node_count as f32),
+            CHART_HAMMING,
+        );
+        draw(x, &mut p_values.range(start..end).copied(), CHART_P);
+    }
+
+    pixels
+}
+
+/// Redraws the chart of the compute model's history, which the P text is
+/// taken from, whenever it steps.
+pub fn update_chart(
+    model_opt: Option<Res<ModelState>>,
+    panels: Query<&ChartPanel>,
+    mut textures: ResMut<Assets<Texture>>,
+    mut drawn: Local<Option<(usize, Option<Range<usize>>)>>,
+) {
+    let model = if let Some(model) = model_opt {
+        model
+    } else {
+        return;
+    };
+
+    let compute_model = &model.compute_model;
+    let key = (compute_model.timestep, model.cycle.clone());
+
+    if !model.is_added() && drawn.as_ref() == Some(&key) {
+        return;
+    }
+    *drawn = Some(key);
+
+    let texture = Texture::new(
+        Extent3d::new(CHART_WIDTH as u32, CHART_HEIGHT as u32, 1),
+        TextureDimension::D2,
+        chart_pixels(
+            &compute_model.history,
+            compute_model.graph.node_count(),
+            &model.cycle,
+        ),
+        TextureFormat::Rgba8UnormSrgb,
+    );
+
+    for panel in panels.iter() {
+        if let Some(panel_texture) = textures.get_mut(&panel.0) {
+            *panel_texture = texture.clone();
+        }
+    }
+}
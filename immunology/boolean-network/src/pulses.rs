@@ -0,0 +1,178 @@
+//! Pulses travelling along the wires of active nodes over each step's
+//! interval, so that causality is visible. Every wire gets a single pulse,
+//! all sharing one mesh and material, and they can be turned off for large
+//! networks.
+
+use bevy::prelude::*;
+
+use crate::{ModelState, NetworkConfig, Playback, SimEdge, SimNode, SimUpdateTimer};
+
+pub struct Pulses {
+    pub enabled: bool,
+}
+
+impl Default for Pulses {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+pub struct PulseButton;
+
+pub struct Pulse;
+
+/// Marks wires that have their pulse.
+pub struct Pulsed;
+
+pub struct PulseAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+impl FromWorld for PulseAssets {
+    fn from_world(world: &mut World) -> Self {
+        let config = world.get_resource::<NetworkConfig>().unwrap().clone();
+
+        let mut meshes = world.get_resource_mut::<Assets<Mesh>>().unwrap();
+        let mesh = meshes.add(
+            shape::Icosphere {
+                radius: config.bulb_radius * config.wire_radius_ratio * 4.0,
+                subdivisions: 1,
+            }
+            .into(),
+        );
+
+        let mut materials = world
+            .get_resource_mut::<Assets<StandardMaterial>>()
+            .unwrap();
+        let material = materials.add(config.colors.pulse.into());
+
+        PulseAssets { mesh, material }
+    }
+}
+
+pub fn pulse_button(
+    interactions: Query<&Interaction, (Changed<Interaction>, With<PulseButton>)>,
+    mut pulses: ResMut<Pulses>,
+) {
+    for interaction in interactions.iter() {
+        if let Interaction::Clicked = interaction {
+            pulses.enabled = !pulses.enabled;
+        }
+    }
+}
+
+pub fn pulse_button_label(
+    pulses: Res<Pulses>,
+    buttons: Query<&Children, With<PulseButton>>,
+    mut texts: Query<&mut Text>,
+) {
+    if !pulses.is_changed() {
+        return;
+    }
+
+    for children in buttons.iter() {
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.sections[0].value = if pulses.enabled {
+                    "Pulses: on"
+                } else {
+                    "Pulses: off"
+                }
+                .to_string();
+            }
+        }
+    }
+}
+
+/// Gives every wire its pulse while pulses are on, and removes them when
+/// they are turned off.
+pub fn spawn_pulses(
+    mut commands: Commands,
+    pulses: Res<Pulses>,
+    assets: Res<PulseAssets>,
+    bare_wires: Query<Entity, (With<SimEdge>, Without<Pulsed>)>,
+    pulsed_wires: Query<Entity, With<Pulsed>>,
+    spawned: Query<Entity, With<Pulse>>,
+) {
+    if !pulses.enabled {
+        if pulses.is_changed() {
+            for pulse in spawned.iter() {
+                commands.entity(pulse).despawn_recursive();
+            }
+
+            for wire in pulsed_wires.iter() {
+                commands.entity(wire).remove::<Pulsed>();
+            }
+        }
+
+        return;
+    }
+
+    for wire in bare_wires.iter() {
+        let pulse = commands
+            .spawn_bundle(PbrBundle {
+                mesh: assets.mesh.clone(),
+                material: assets.material.clone(),
+                visible: Visible {
+                    is_visible: false,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .insert(Pulse)
+            .id();
+
+        commands.entity(wire).insert(Pulsed).push_children(&[pulse]);
+    }
+}
+
+/// Moves each pulse from its wire's source to its target as the interval to
+/// the next step passes, showing those of active sources while playing.
+pub fn animate_pulses(
+    timer: Res<SimUpdateTimer>,
+    playback: Res<Playback>,
+    model_opt: Option<Res<ModelState>>,
+    nodes: Query<&SimNode>,
+    wires: Query<(&SimEdge, &Transform, &Children), Without<Pulse>>,
+    mut pulses: Query<(&mut Transform, &mut Visible), With<Pulse>>,
+) {
+    let model = if let Some(model) = model_opt {
+        model
+    } else {
+        return;
+    };
+
+    let graph = &model.display_model.graph;
+    let mut active = vec![false; graph.node_count()];
+    for node in nodes.iter() {
+        if let Some(active) = active.get_mut(node.graph_id as usize) {
+            *active = node.active;
+        }
+    }
+
+    let progress = timer.0.percent();
+
+    for (wire, wire_transform, children) in wires.iter() {
+        let edge = match graph.raw_edges().get(wire.graph_id as usize) {
+            Some(edge) => edge,
+            None => continue,
+        };
+
+        let fired = playback.playing && active[edge.source().index()];
+
+        // Wires run along their local y axis, which may point either way.
+        let direction = graph[edge.target()].position - graph[edge.source()].position;
+        let sign = (wire_transform.rotation * Vec3::Y).dot(direction).signum();
+
+        for child in children.iter() {
+            if let Ok((mut transform, mut visible)) = pulses.get_mut(*child) {
+                visible.is_visible = fired;
+
+                if fired {
+                    transform.translation = Vec3::Y * sign * (progress - 0.5) * wire.len;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! The web page's session, kept in the browser's storage so that reloading
+//! the page carries on where it left off: the network as a snapshot, in the
+//! format the Save button writes natively, and the viewer's settings. The
+//! generator panel saves its settings itself.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arrange::Arranger, overlay::Overlay, pulses::Pulses, spawn_button, storage, store_snapshot,
+    stored_snapshot, ButtonMaterials, ErrorMessage, GeneratorSettings, ModelState, NetworkConfig,
+    Playback, RegenerateEvent, MAX_SPEED, MIN_SPEED,
+};
+
+const SNAPSHOT_KEY: &str = "session.json";
+const VIEW_KEY: &str = "view.json";
+
+const AUTOSAVE_SECONDS: f32 = 5.0;
+
+pub struct ResetButton;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ViewSettings {
+    playing: bool,
+    speed: f32,
+    looping: bool,
+    heat: bool,
+    pulses: bool,
+    animate_layout: bool,
+}
+
+impl ViewSettings {
+    fn new(playback: &Playback, overlay: &Overlay, pulses: &Pulses, arranger: &Arranger) -> Self {
+        Self {
+            playing: playback.playing,
+            speed: playback.speed,
+            looping: playback.looping,
+            heat: overlay.enabled,
+            pulses: pulses.enabled,
+            animate_layout: arranger.animate,
+        }
+    }
+
+    fn apply(&self, world: &mut World) {
+        {
+            let mut playback = world.get_resource_mut::<Playback>().unwrap();
+            playback.playing = self.playing;
+            // `max` also replaces NaN.
+            playback.speed = self.speed.max(MIN_SPEED).min(MAX_SPEED);
+            playback.looping = self.looping;
+        }
+
+        world.get_resource_mut::<Overlay>().unwrap().enabled = self.heat;
+        world.get_resource_mut::<Pulses>().unwrap().enabled = self.pulses;
+        world.get_resource_mut::<Arranger>().unwrap().animate = self.animate_layout;
+    }
+}
+
+/// Restores the last session if `restore` is set, and keeps saving it.
+pub fn build(app: &mut AppBuilder, restore: bool) {
+    if restore {
+        let world = app.world_mut();
+
+        if let Err(err) = restore_session(world) {
+            let mut error_message = world.get_resource_mut::<ErrorMessage>().unwrap();
+            error_message.show(format!("failed to restore the last session: {}", err));
+        }
+    }
+
+    if app
+        .world()
+        .get_resource::<NetworkConfig>()
+        .unwrap()
+        .spawn_ui
+    {
+        app.add_startup_system(setup_reset_button.system())
+            .add_system(reset_button.system());
+    }
+
+    app.add_system(autosave.system());
+}
+
+fn restore_session(world: &mut World) -> Result<(), String> {
+    if let Some(json) = storage::read(VIEW_KEY)? {
+        let view = serde_json::from_str::<ViewSettings>(&json).map_err(|err| err.to_string())?;
+        view.apply(world);
+    }
+
+    // `setup` only generates a network when none was restored.
+    if let Some(snapshot) = stored_snapshot(SNAPSHOT_KEY)? {
+        world.insert_resource(ModelState::restore(snapshot)?);
+    }
+
+    Ok(())
+}
+
+fn setup_reset_button(
+    mut commands: Commands,
+    button_materials: Res<ButtonMaterials>,
+    asset_server: Res<AssetServer>,
+) {
+    spawn_button(
+        &mut commands,
+        &button_materials,
+        &asset_server,
+        "Reset",
+        780.0,
+        0.0,
+        140.0,
+    )
+    .insert(ResetButton);
+}
+
+/// Forgets the session and starts over with the default settings.
+#[allow(clippy::too_many_arguments)]
+fn reset_button(
+    interactions: Query<&Interaction, (Changed<Interaction>, With<Button>, With<ResetButton>)>,
+    config: Res<NetworkConfig>,
+    mut settings: ResMut<GeneratorSettings>,
+    mut playback: ResMut<Playback>,
+    mut overlay: ResMut<Overlay>,
+    mut pulses: ResMut<Pulses>,
+    mut arranger: ResMut<Arranger>,
+    mut events: EventWriter<RegenerateEvent>,
+) {
+    for interaction in interactions.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+
+        for key in [SNAPSHOT_KEY, VIEW_KEY] {
+            if let Err(err) = storage::remove(key) {
+                warn!("failed to remove {}: {}", key, err);
+            }
+        }
+
+        *playback = Playback {
+            speed: config.initial_speed(),
+            ..Default::default()
+        };
+        *overlay = Overlay::default();
+        *pulses = Pulses::default();
+        *arranger = Arranger::default();
+
+        // Generating saves the settings.
+        *settings = GeneratorSettings::default();
+        events.send(RegenerateEvent(settings.clone()));
+    }
+}
+
+/// Saves the session every few seconds. The display model's node states are
+/// left out, which keeps the snapshot within the browser's storage quota at
+/// the cost of the history to rewind through.
+#[allow(clippy::too_many_arguments)]
+fn autosave(
+    time: Res<Time>,
+    mut since_saved: Local<f32>,
+    mut failing: Local<bool>,
+    model_opt: Option<Res<ModelState>>,
+    playback: Res<Playback>,
+    overlay: Res<Overlay>,
+    pulses: Res<Pulses>,
+    arranger: Res<Arranger>,
+    mut error_message: ResMut<ErrorMessage>,
+) {
+    *since_saved += time.delta_seconds();
+    if *since_saved < AUTOSAVE_SECONDS {
+        return;
+    }
+    *since_saved = 0.0;
+
+    let model = if let Some(model) = model_opt {
+        model
+    } else {
+        return;
+    };
+
+    let mut snapshot = model.snapshot();
+    snapshot.display.history = std::mem::take(&mut snapshot.display.history).without_states();
+
+    let view = ViewSettings::new(&playback, &overlay, &pulses, &arranger);
+
+    let result = store_snapshot(SNAPSHOT_KEY, &snapshot).and_then(|()| {
+        let json = serde_json::to_string(&view).map_err(|err| err.to_string())?;
+        storage::write(VIEW_KEY, &json)
+    });
+
+    match result {
+        Ok(()) => *failing = false,
+        // Shown once rather than every few seconds.
+        Err(err) if !*failing => {
+            *failing = true;
+            error_message.show(format!("failed to save the session: {}", err));
+        }
+        Err(_) => {}
+    }
+}
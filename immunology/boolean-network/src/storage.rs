@@ -0,0 +1,50 @@
+//! Documents kept from one run to the next: files in the working directory
+//! natively, and entries of the page's `localStorage` on the web.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::{fs, io::ErrorKind};
+
+/// The document saved as `name`, if any.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read(name: &str) -> Result<Option<String>, String> {
+    match fs::read_to_string(name) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write(name: &str, contents: &str) -> Result<(), String> {
+    fs::write(name, contents).map_err(|err| err.to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Result<web_sys::Storage, String> {
+    web_sys::window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .ok_or_else(|| "browser storage is unavailable".to_string())
+}
+
+/// The document saved as `name`, if any.
+#[cfg(target_arch = "wasm32")]
+pub fn read(name: &str) -> Result<Option<String>, String> {
+    local_storage()?
+        .get_item(name)
+        .map_err(|err| format!("{:?}", err))
+}
+
+/// Fails once the page's storage quota is used up.
+#[cfg(target_arch = "wasm32")]
+pub fn write(name: &str, contents: &str) -> Result<(), String> {
+    local_storage()?
+        .set_item(name, contents)
+        .map_err(|err| format!("{:?}", err))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn remove(name: &str) -> Result<(), String> {
+    local_storage()?
+        .remove_item(name)
+        .map_err(|err| format!("{:?}", err))
+}
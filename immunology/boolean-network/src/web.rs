@@ -0,0 +1,189 @@
+//! Loading networks on the web page, so that exact networks can be shared as
+//! links: the query string sets the generator's seed and node count
+//! (`?seed=42&n=100`), and a snapshot (`.json`), BoolNet (`.bnet`) or GraphML
+//! (`.graphml`) file dropped onto the page replaces the network.
+
+use std::sync::{Arc, Mutex};
+
+use bevy::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
+use wasm_bindgen::{prelude::*, JsCast};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::DragEvent;
+
+use crate::{
+    generator::MAX_NODE_COUNT,
+    model::{
+        generate::GeneratorParams,
+        interchange,
+        layout::{self, Algorithm, Layout},
+        snapshot::Snapshot,
+        NodeKind,
+    },
+    replace_model, ErrorMessage, GeneratorSettings, ModelState,
+};
+
+/// Names and contents of the files dropped onto the page since the last
+/// frame, filled in by the page's event listeners.
+#[derive(Default, Clone)]
+struct DroppedFiles(Arc<Mutex<Vec<(String, Result<String, String>)>>>);
+
+/// Applies the query string to the generator settings and listens for
+/// dropped files. Returns whether the query string chose the network.
+pub fn build(app: &mut AppBuilder) -> bool {
+    let query = web_sys::window()
+        .and_then(|window| window.location().search().ok())
+        .unwrap_or_default();
+
+    let world = app.world_mut();
+    let result = apply_query(
+        &mut world.get_resource_mut::<GeneratorSettings>().unwrap(),
+        &query,
+    );
+
+    let linked = result.unwrap_or_else(|err| {
+        let mut error_message = world.get_resource_mut::<ErrorMessage>().unwrap();
+        error_message.show(format!("ignoring the page's parameters: {}", err));
+        false
+    });
+
+    let dropped = DroppedFiles::default();
+    listen_for_drops(dropped.clone());
+
+    app.insert_resource(dropped)
+        .add_system(load_dropped.system());
+
+    linked
+}
+
+/// Sets the seed and node count from `seed` and `n` in `query`, ignoring other
+/// parameters, and returns whether either was given. Nothing is set if any of
+/// them is invalid.
+fn apply_query(settings: &mut GeneratorSettings, query: &str) -> Result<bool, String> {
+    let mut parsed = settings.clone();
+    let mut given = false;
+
+    for pair in query.trim_start_matches('?').split('&') {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+
+        match key {
+            "seed" => {
+                let seed = value
+                    .parse()
+                    .map_err(|_| format!("invalid seed {:?}", value))?;
+                parsed.seed = Some(seed);
+                given = true;
+            }
+            "n" => {
+                parsed.params.node_count = match value.parse() {
+                    Ok(n) if (1..=MAX_NODE_COUNT).contains(&n) => n,
+                    _ => {
+                        return Err(format!(
+                            "invalid node count {:?}, expected 1 to {}",
+                            value, MAX_NODE_COUNT
+                        ))
+                    }
+                };
+                given = true;
+            }
+            _ => {}
+        }
+    }
+
+    *settings = parsed;
+    Ok(given)
+}
+
+fn listen_for_drops(dropped: DroppedFiles) {
+    let window = if let Some(window) = web_sys::window() {
+        window
+    } else {
+        return;
+    };
+
+    // The page only accepts drops where dragging over it is cancelled.
+    let dragover = Closure::wrap(Box::new(|event: DragEvent| {
+        event.prevent_default();
+    }) as Box<dyn FnMut(DragEvent)>);
+
+    let drop = Closure::wrap(Box::new(move |event: DragEvent| {
+        event.prevent_default();
+
+        let files = if let Some(files) = event.data_transfer().and_then(|data| data.files()) {
+            files
+        } else {
+            return;
+        };
+
+        for file in (0..files.length()).filter_map(|index| files.get(index)) {
+            let dropped = dropped.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                let contents = JsFuture::from(file.text())
+                    .await
+                    .ok()
+                    .and_then(|text| text.as_string())
+                    .ok_or_else(|| "failed to read the file".to_string());
+
+                dropped.0.lock().unwrap().push((file.name(), contents));
+            });
+        }
+    }) as Box<dyn FnMut(DragEvent)>);
+
+    for (name, listener) in [("dragover", &dragover), ("drop", &drop)] {
+        if let Err(err) =
+            window.add_event_listener_with_callback(name, listener.as_ref().unchecked_ref())
+        {
+            warn!("failed to listen for {}: {:?}", name, err);
+        }
+    }
+
+    // The listeners stay for the lifetime of the page.
+    dragover.forget();
+    drop.forget();
+}
+
+fn load_dropped(
+    mut commands: Commands,
+    dropped: Res<DroppedFiles>,
+    mut error_message: ResMut<ErrorMessage>,
+) {
+    let files = std::mem::take(&mut *dropped.0.lock().unwrap());
+
+    for (name, contents) in files {
+        match contents.and_then(|contents| load(&name, &contents)) {
+            Ok(model) => replace_model(&mut commands, model),
+            Err(err) => error_message.show(format!("failed to load {}: {}", name, err)),
+        }
+    }
+}
+
+/// Reads a simulation from a file of the type given by its extension.
+/// BoolNet files have no positions, so they are laid out force-directed with a
+/// fixed seed, the same for everyone loading a file. GraphML nodes without
+/// positions are placed on a circle by `read_graphml`.
+fn load(name: &str, contents: &str) -> Result<ModelState, String> {
+    let extension = name.rsplit('.').next().unwrap_or("").to_ascii_lowercase();
+
+    let model = match extension.as_str() {
+        "json" => return ModelState::restore(Snapshot::read(contents.as_bytes())?),
+        "bnet" => {
+            let mut model = interchange::read_bnet(contents)?;
+            let mut rng = StdRng::seed_from_u64(0);
+            let positions =
+                Layout::new(Algorithm::Force, &model, layout::SPACING, &mut rng).finish();
+
+            layout::apply(&mut model, &positions);
+            model
+        }
+        "graphml" => interchange::read_graphml(contents, NodeKind::Or(false))?,
+        _ => {
+            return Err(format!(
+                "unknown file type {:?}, expected .json, .bnet or .graphml",
+                extension
+            ))
+        }
+    };
+
+    Ok(ModelState::new(model, GeneratorParams::default(), 0))
+}
@@ -0,0 +1,279 @@
+//! Runs the cycle search of the compute model off the main thread, which
+//! polls its latest progress every frame without waiting on it.
+//!
+//! Natively the search runs on a thread of its own. The web build has no
+//! threads, so there it runs in a Web Worker, the `search-worker` binary,
+//! which is sent the network once and posts its progress back as JSON.
+
+#[cfg(target_arch = "wasm32")]
+use std::{cell::RefCell, rc::Rc};
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+#[cfg(target_arch = "wasm32")]
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::{prelude::*, JsCast};
+#[cfg(target_arch = "wasm32")]
+use web_sys::{DedicatedWorkerGlobalScope, ErrorEvent, MessageEvent, Worker};
+
+use crate::model::search::Search;
+#[cfg(target_arch = "wasm32")]
+use crate::model::search::{Progress, Request};
+
+/// Steps taken between checks for cancellation or progress reports.
+const SLICE: usize = 256;
+
+/// Minimum time between progress reports.
+const REPORT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Script starting the `search-worker` binary, as built by Trunk.
+#[cfg(target_arch = "wasm32")]
+const WORKER_URL: &str = "./search-worker_loader.js";
+
+/// Posted by the worker for each request.
+#[cfg(target_arch = "wasm32")]
+#[derive(Serialize, Deserialize)]
+enum Message {
+    Progress(Progress),
+    Failed(String),
+}
+
+/// A running (or finished) search, cancelled when dropped. Polled through a
+/// shared reference, so that holders aren't marked changed every frame.
+pub struct ComputeWorker {
+    finished: AtomicBool,
+    /// Latest progress not polled yet.
+    #[cfg(not(target_arch = "wasm32"))]
+    latest: Arc<Mutex<Option<Search>>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    cancelled: Arc<AtomicBool>,
+    /// The search as last reported by the worker.
+    #[cfg(target_arch = "wasm32")]
+    search: RefCell<Search>,
+    #[cfg(target_arch = "wasm32")]
+    worker: Result<Worker, String>,
+    /// Latest message (or error) from the worker not polled yet.
+    #[cfg(target_arch = "wasm32")]
+    latest: Rc<RefCell<Option<Result<String, String>>>>,
+    #[cfg(target_arch = "wasm32")]
+    _listeners: (
+        Closure<dyn FnMut(MessageEvent)>,
+        Closure<dyn FnMut(ErrorEvent)>,
+    ),
+}
+
+// Bevy resources must be `Send` and `Sync`, and the web build only ever has
+// the one thread to share this with.
+#[cfg(target_arch = "wasm32")]
+unsafe impl Send for ComputeWorker {}
+#[cfg(target_arch = "wasm32")]
+unsafe impl Sync for ComputeWorker {}
+
+impl ComputeWorker {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn spawn(mut search: Search) -> Self {
+        let latest = Arc::new(Mutex::new(None));
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let worker = Self {
+            finished: AtomicBool::new(false),
+            latest: latest.clone(),
+            cancelled: cancelled.clone(),
+        };
+
+        std::thread::spawn(move || {
+            let mut reported = Instant::now();
+
+            loop {
+                let done = search.run(SLICE);
+
+                if cancelled.load(Ordering::Relaxed) {
+                    return;
+                }
+
+                if done || reported.elapsed() >= REPORT_INTERVAL {
+                    *latest.lock().unwrap() = Some(search.clone());
+                    reported = Instant::now();
+                }
+
+                if done {
+                    return;
+                }
+            }
+        });
+
+        worker
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn spawn(search: Search) -> Self {
+        let latest = Rc::new(RefCell::new(None));
+
+        let onmessage = {
+            let latest = latest.clone();
+            Closure::wrap(Box::new(move |event: MessageEvent| {
+                let json = event.data().as_string();
+                *latest.borrow_mut() = Some(
+                    json.ok_or_else(|| "unexpected message from the search worker".to_string()),
+                );
+            }) as Box<dyn FnMut(MessageEvent)>)
+        };
+
+        let onerror = {
+            let latest = latest.clone();
+            Closure::wrap(Box::new(move |event: ErrorEvent| {
+                *latest.borrow_mut() = Some(Err(format!("search worker: {}", event.message())));
+            }) as Box<dyn FnMut(ErrorEvent)>)
+        };
+
+        let worker = Worker::new(WORKER_URL)
+            .map_err(|err| format!("failed to start the search worker: {:?}", err))
+            .and_then(|worker| {
+                worker.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+                worker.set_onerror(Some(onerror.as_ref().unchecked_ref()));
+
+                let request =
+                    serde_json::to_string(&search.request()).map_err(|err| err.to_string())?;
+                worker
+                    .post_message(&JsValue::from_str(&request))
+                    .map_err(|err| format!("failed to start the search: {:?}", err))?;
+
+                Ok(worker)
+            });
+
+        Self {
+            finished: AtomicBool::new(false),
+            search: RefCell::new(search),
+            worker,
+            latest,
+            _listeners: (onmessage, onerror),
+        }
+    }
+
+    /// Progress since the last poll, if any. A search that failed is
+    /// finished.
+    pub fn poll(&self) -> Result<Option<Search>, String> {
+        #[cfg(not(target_arch = "wasm32"))]
+        let search = match self.latest.lock().unwrap().take() {
+            Some(search) => search,
+            None => return Ok(None),
+        };
+
+        #[cfg(target_arch = "wasm32")]
+        let search = {
+            if self.is_finished() {
+                return Ok(None);
+            }
+
+            let message = match &self.worker {
+                Err(err) => Err(err.clone()),
+                Ok(_) => match self.latest.borrow_mut().take() {
+                    Some(message) => message,
+                    None => return Ok(None),
+                },
+            }
+            .and_then(|json| serde_json::from_str::<Message>(&json).map_err(|err| err.to_string()));
+
+            let result = message.and_then(|message| match message {
+                Message::Progress(progress) => self.search.borrow_mut().apply(progress),
+                Message::Failed(err) => Err(err),
+            });
+
+            if let Err(err) = result {
+                self.finished.store(true, Ordering::Relaxed);
+                return Err(err);
+            }
+
+            self.search.borrow().clone()
+        };
+
+        self.finished.store(search.is_done(), Ordering::Relaxed);
+        Ok(Some(search))
+    }
+
+    /// Whether the search has ended and its final progress has been polled.
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for ComputeWorker {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn drop(&mut self) {
+        // The listeners are dropped along with this.
+        if let Ok(worker) = &self.worker {
+            worker.set_onmessage(None);
+            worker.set_onerror(None);
+            worker.terminate();
+        }
+    }
+}
+
+/// Runs the searches requested by the page, in the `search-worker` binary.
+/// Each request is stepped to the end, posting its progress as it goes; the
+/// page terminates the worker to cancel it.
+#[cfg(target_arch = "wasm32")]
+pub fn serve() {
+    let scope = js_sys::global().unchecked_into::<DedicatedWorkerGlobalScope>();
+
+    let onmessage = Closure::wrap(Box::new(|event: MessageEvent| {
+        let scope = js_sys::global().unchecked_into::<DedicatedWorkerGlobalScope>();
+        let post = |message: &Message| {
+            let result = serde_json::to_string(message)
+                .map_err(|err| format!("{:?}", err))
+                .and_then(|json| {
+                    scope
+                        .post_message(&JsValue::from_str(&json))
+                        .map_err(|err| format!("{:?}", err))
+                });
+
+            if let Err(err) = result {
+                web_sys::console::error_1(&JsValue::from_str(&err));
+            }
+        };
+
+        let search = event
+            .data()
+            .as_string()
+            .ok_or_else(|| "expected a search request".to_string())
+            .and_then(|json| serde_json::from_str::<Request>(&json).map_err(|err| err.to_string()))
+            .and_then(Request::into_search);
+
+        let mut search = match search {
+            Ok(search) => search,
+            Err(err) => return post(&Message::Failed(err)),
+        };
+
+        let mut reported = js_sys::Date::now();
+
+        loop {
+            let done = search.run(SLICE);
+
+            if done || js_sys::Date::now() - reported >= REPORT_INTERVAL.as_secs_f64() * 1e3 {
+                post(&Message::Progress(search.progress()));
+                reported = js_sys::Date::now();
+            }
+
+            if done {
+                return;
+            }
+        }
+    }) as Box<dyn FnMut(MessageEvent)>);
+
+    scope.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+    // The listener stays for the lifetime of the worker.
+    onmessage.forget();
+}